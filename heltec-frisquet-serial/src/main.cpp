@@ -105,9 +105,10 @@ void loop()
         {
             int len = radio.getPacketLength();
             int rssi = radio.getRSSI();
-            Serial.printf("%02X", len, rssi);
+            Serial.printf("%02X", len);
             for (int i = 0; i < len; i++)
                 Serial.printf("%02X", byteArr[i]);
+            Serial.printf(" %d", rssi);
             Serial.println("");
             receiveddCount++;
             updateDisplay(len, byteArr, rssi);
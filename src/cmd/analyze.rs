@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fs::{read_to_string, File};
+use std::io::Write;
+
+use crate::config::Config;
+use crate::connect::analyze::Analyzer;
+use crate::rf::RFClient;
+
+/// Analyze frames from a capture file, one hex encoded frame per line as
+/// saved by `--save`.
+pub fn read(file: &str) -> Result<(), Box<dyn Error>> {
+    let mut analyzer = Analyzer::new();
+    for line in read_to_string(file)?.lines() {
+        match hex::decode(line.trim()) {
+            Ok(frame) if frame.len() >= 7 => {
+                if let Err(e) = analyzer.push(&frame) {
+                    println!("skip {}: {}", line, e);
+                }
+            }
+            _ => println!("skip {}", line),
+        }
+    }
+
+    report(&analyzer);
+    Ok(())
+}
+
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    count: usize,
+    save: &Option<String>,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    let mut analyzer = Analyzer::new();
+    let mut out = match save {
+        Some(path) => Some(File::create(path)?),
+        None => None,
+    };
+    rf.set_network_id(config.frisquet()?.network_id()?)?;
+
+    for n in 0..count {
+        let frame = rf.recv()?;
+        if frame.len() < 7 {
+            continue;
+        }
+        println!("[{}/{}] {}", n + 1, count, hex::encode(&frame));
+        if let Some(out) = &mut out {
+            writeln!(out, "{}", hex::encode(&frame))?;
+        }
+        analyzer.push(&frame)?;
+    }
+
+    report(&analyzer);
+    Ok(())
+}
+
+fn report(analyzer: &Analyzer) {
+    for group in analyzer.report() {
+        println!("{}\n", group);
+    }
+}
@@ -1,12 +1,88 @@
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime};
 use std::error::Error;
 
-use crate::config::Config;
-use crate::connect::area::connect_area1;
+use crate::config::{Config, ConfigError, OVERRIDE_UNTIL_FORMAT};
+use crate::connect::area::{connect_area, connect_area1, try_connect_area};
 use crate::rf::RFClient;
 
-pub fn run(rf: &mut Box<dyn RFClient>, config: &mut Config) -> Result<(), Box<dyn Error>> {
-    let config = &mut *config;
-    let (_meta, _sensor) = connect_area1(rf, config)?;
+/// Change to the configured zone 1 program.
+pub enum Patch {
+    Mode(String),
+    Boost(bool),
+    Override { mode: String, until: Option<String> },
+}
+
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    patch: Option<Patch>,
+    save: bool,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    let Some(patch) = patch else {
+        let (_meta, _sensor) = connect_area1(rf, config)?;
+        return Ok(());
+    };
+
+    let mut prog = config.area1()?.clone();
+    // the daemon reverts expiring overrides from the config
+    let save = save || matches!(patch, Patch::Override { until: Some(_), .. });
+    match patch {
+        Patch::Mode(mode) => prog.mode = mode,
+        Patch::Boost(boost) => prog.boost = boost,
+        Patch::Override { mode, until } => {
+            prog.override_until = match until {
+                Some(_) if mode == "none" => Err("--until requires an override")?,
+                Some(until) => Some(
+                    next_occurrence(&until, Local::now().naive_local())?
+                        .format(OVERRIDE_UNTIL_FORMAT)
+                        .to_string(),
+                ),
+                None => None,
+            };
+            prog.r#override = mode;
+        }
+    }
 
+    let (_meta, _sensor) = connect_area(rf, config.frisquet()?, &prog)?;
+    if save {
+        config.update(|config| {
+            *config.area1()? = prog;
+            Ok::<_, ConfigError>(())
+        })?;
+    }
     Ok(())
 }
+
+/// Revert the zone 1 override once its end time is reached.
+pub fn revert_expired_override(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    match &config.area1 {
+        Some(area) if area.override_expired(Local::now().naive_local())? => {}
+        _ => return Ok(()),
+    }
+
+    println!("Override expired, back to schedule");
+    let mut prog = config.area1()?.clone();
+    prog.r#override = "none".into();
+    prog.override_until = None;
+    let (_meta, _sensor) = try_connect_area(rf, config.frisquet()?, &prog)?;
+    config.update(|config| {
+        *config.area1()? = prog;
+        Ok::<_, ConfigError>(())
+    })?;
+    Ok(())
+}
+
+/// Next `HH:MM` after `now`, today or tomorrow.
+fn next_occurrence(time: &str, now: NaiveDateTime) -> Result<NaiveDateTime, Box<dyn Error>> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| format!("invalid time: {}, expected HH:MM", time))?;
+    let end = now.date().and_time(time);
+    Ok(if end <= now {
+        end + Duration::days(1)
+    } else {
+        end
+    })
+}
@@ -0,0 +1,98 @@
+use chrono::{Local, NaiveDateTime};
+use colored::Colorize;
+use std::error::Error;
+use std::time::Instant;
+
+use crate::config::{CalendarConfig, Config, ConfigError, OVERRIDE_UNTIL_FORMAT};
+use crate::connect::area::try_connect_area;
+use crate::connect::holiday::connect_holiday;
+use crate::datasource::calendar::{active_override, next_holiday, Calendar};
+use crate::rf::RFClient;
+
+/// Print the heating events of the calendar, without using the radio.
+pub fn show(config: &Config) -> Result<(), Box<dyn Error>> {
+    let calendar = config.calendar.as_ref().ok_or("no calendar configured")?;
+    let mut calendar = Calendar::new(calendar);
+    let events = calendar.events(Instant::now())?;
+    if events.is_empty() {
+        println!("no heating event");
+        return Ok(());
+    }
+
+    let now = Local::now().naive_local();
+    let active = active_override(events, now);
+    let holiday = next_holiday(events, now);
+    for event in events {
+        if Some(event) == active {
+            println!("{} (active)", event.to_string().green());
+        } else if Some(event) == holiday {
+            println!("{} (next holiday)", event.to_string().green());
+        } else if event.end <= now {
+            println!("{}", event.to_string().dimmed());
+        } else {
+            println!("{}", event);
+        }
+    }
+    Ok(())
+}
+
+/// Daemon side of the calendar: overrides area1 during comfort and reduced
+/// events and writes the boiler holiday period.
+pub struct CalendarSync {
+    calendar: Calendar,
+    write_holidays: bool,
+    /// holiday period last written, `None` before the first write
+    holiday: Option<Option<(NaiveDateTime, NaiveDateTime)>>,
+}
+
+impl CalendarSync {
+    pub fn new(config: &CalendarConfig) -> CalendarSync {
+        CalendarSync {
+            calendar: Calendar::new(config),
+            write_holidays: config.write_holidays.unwrap_or(false),
+            holiday: None,
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        rf: &mut Box<dyn RFClient>,
+        config: &mut Config,
+    ) -> Result<(), Box<dyn Error>> {
+        let now = Local::now().naive_local();
+        let events = self.calendar.events(Instant::now())?;
+
+        // the override is reverted by `run` when the event ends
+        if let Some(event) = active_override(events, now) {
+            let mut prog = config.area1()?.clone();
+            let mode = event.kind.to_string();
+            let until = event.end.format(OVERRIDE_UNTIL_FORMAT).to_string();
+            if prog.r#override != mode || prog.override_until.as_ref() != Some(&until) {
+                println!("Calendar override: {}", event);
+                prog.r#override = mode;
+                prog.override_until = Some(until);
+                let (_meta, _sensor) = try_connect_area(rf, config.frisquet()?, &prog)?;
+                config.update(|config| {
+                    *config.area1()? = prog;
+                    Ok::<_, ConfigError>(())
+                })?;
+            }
+        }
+
+        // holidays set elsewhere are only replaced, never cleared
+        let period = next_holiday(events, now).map(|e| (e.start, e.end));
+        if self.holiday != Some(period) && (period.is_some() || self.holiday.is_some()) {
+            match period {
+                Some((start, end)) => println!("Calendar holiday: {} - {}", start, end),
+                None => println!("Calendar holiday cleared"),
+            }
+            if self.write_holidays {
+                let (_meta, _reply) = connect_holiday(rf, config.frisquet()?, period)?;
+            } else {
+                println!("Holiday not sent to the boiler, set calendar.write_holidays");
+            }
+            self.holiday = Some(period);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::fs::{copy, read_to_string, rename, write};
+
+use crate::config;
+use crate::config::migrate::{self, VERSION};
+
+/// Upgrade the config file in place, the previous one kept as
+/// `<file>.v<version>.bak`.
+pub fn migrate(path: &str, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    let Some((from, migrated)) = migrate::migrate(&content)? else {
+        println!("{} is already version {}", path, VERSION);
+        return Ok(());
+    };
+    if dry_run {
+        print!("{}", migrated);
+        return Ok(());
+    }
+
+    let backup = format!("{}.v{}.bak", path, from);
+    copy(path, &backup)?;
+    let tmp = format!("{}.tmp", path);
+    write(&tmp, migrated)?;
+    rename(&tmp, path)?;
+    println!(
+        "{} upgraded from version {} to {}, previous file saved as {}",
+        path, from, VERSION, backup
+    );
+    Ok(())
+}
+
+/// Print the JSON Schema of the config, or write it to a file.
+pub fn schema(output: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let schema = config::schema()?;
+    match output {
+        Some(path) => write(path, schema + "\n")?,
+        None => println!("{}", schema),
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use deku::DekuError;
+use serde_json::json;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io::{stdin, BufRead};
+
+use crate::cmd::Format;
+use crate::connect::decoder::{DecodedFrame, Registry, Session};
+
+/// Decode hex encoded frames given as arguments, or one per line from a
+/// file or stdin.
+pub fn run(frames: &[String], format: Format, file: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let lines: Vec<String> = match (frames, file) {
+        ([], Some(file)) => read_to_string(file)?.lines().map(String::from).collect(),
+        ([], None) => stdin().lock().lines().collect::<Result<_, _>>()?,
+        (frames, _) => frames.to_vec(),
+    };
+
+    let mut session = Session::new(Registry::with_known_messages());
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match hex::decode(line) {
+            Ok(frame) => print_frame(format, &frame, session.decode(&frame)),
+            Err(e) => match format {
+                Format::Text => println!("invalid hex: {} - {}", e, line),
+                Format::Json => println!("{}", json!({ "raw": line, "error": e.to_string() })),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+pub fn print_frame(format: Format, raw: &[u8], frame: Result<DecodedFrame, DekuError>) {
+    match (format, frame) {
+        (Format::Text, Ok(frame)) => println!("{}", frame),
+        (Format::Text, Err(e)) => println!("meta err: {} - {}", e, hex::encode(raw)),
+        (Format::Json, Ok(frame)) => println!("{}", frame.to_json()),
+        (Format::Json, Err(e)) => println!(
+            "{}",
+            json!({ "raw": hex::encode(raw), "error": e.to_string() })
+        ),
+    }
+}
@@ -0,0 +1,55 @@
+use std::error::Error;
+
+use crate::config::Config;
+
+pub fn list(config: &mut Config) -> Result<(), Box<dyn Error>> {
+    for (name, device) in &config.devices {
+        let association = &device.association;
+        println!(
+            "{}: {}, network_id: {}, association_id: {}, request_id: {}",
+            name,
+            device.role,
+            association
+                .network_id
+                .map(hex::encode)
+                .unwrap_or("-".into()),
+            association
+                .association_id
+                .map(|id| format!("{:02x}", id))
+                .unwrap_or("-".into()),
+            association
+                .request_id
+                .map(|id| format!("{:02x}", id))
+                .unwrap_or("-".into()),
+        );
+    }
+
+    Ok(())
+}
+
+/// Forget a device association, the boiler is not notified.
+pub fn unpair(name: &str, config: &mut Config) -> Result<(), Box<dyn Error>> {
+    config.update(|config| match config.devices.remove(name) {
+        Some(device) => {
+            println!("{} ({}) removed", name, device.role);
+            Ok(())
+        }
+        None => Err(format!("unknown device: {}", name))?,
+    })
+}
+
+pub fn rename(from: &str, to: &str, config: &mut Config) -> Result<(), Box<dyn Error>> {
+    config.update(|config| {
+        if config.devices.contains_key(to) {
+            Err(format!("device {} already exists", to))?;
+        }
+        match config.devices.remove(from) {
+            Some(device) => {
+                config.devices.insert(to.into(), device);
+                println!("{} renamed to {}", from, to);
+                Ok(())
+            }
+            None => Err(format!("unknown device: {}", from))?,
+        }
+    })
+}
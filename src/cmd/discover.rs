@@ -0,0 +1,57 @@
+use chrono::Local;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::connect::discover::Inventory;
+use crate::rf::RFClient;
+
+/// Listen on a network id, broadcast by default, and print every device
+/// seen.
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    network_id: &Option<String>,
+    duration: u64,
+) -> Result<(), Box<dyn Error>> {
+    let network_id = match network_id {
+        Some(network_id) => hex::decode(network_id)?,
+        None => vec![0xff, 0xff, 0xff, 0xff],
+    };
+    if network_id.len() != 4 {
+        Err("network id should be 4 bytes, e.g. ffffffff")?;
+    }
+    rf.set_network_id(network_id.clone())?;
+    println!(
+        "Listening on {} for {}s",
+        hex::encode(&network_id),
+        duration
+    );
+
+    let mut inventory = Inventory::new();
+    let end = Instant::now() + Duration::from_secs(duration);
+    while let Some(wait) = end.checked_duration_since(Instant::now()) {
+        let frame = match rf.recv_timeout(wait) {
+            Ok(frame) => frame,
+            Err(e) if e.is_timeout() => break,
+            Err(e) => Err(e)?,
+        };
+        let known = inventory.devices().len();
+        if let Err(e) = inventory.push(&frame, rf.rssi(), Local::now()) {
+            println!("meta err: {} - {}", e, hex::encode(&frame));
+            continue;
+        }
+        if inventory.devices().len() > known {
+            let addrs: Vec<String> = inventory
+                .devices()
+                .keys()
+                .map(|addr| format!("0x{:02x}", addr))
+                .collect();
+            println!("seen: {}", addrs.join(", "));
+        }
+    }
+
+    println!("\n{}", inventory);
+    for addr in inventory.unknown() {
+        println!("unknown address 0x{:02x}", addr);
+    }
+    Ok(())
+}
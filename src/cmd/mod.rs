@@ -2,19 +2,30 @@ use clap::{Parser, Subcommand, ValueEnum};
 use std::error::Error;
 
 use crate::config::Config;
-use crate::rf::RFClient;
+use crate::config::Role;
+use crate::connect::temperature::Celsius;
+use crate::rf;
 
+pub mod analyze;
 pub mod area1;
+pub mod calendar;
+pub mod config;
 pub mod data1;
 pub mod data2;
 pub mod data3;
 pub mod data4;
 pub mod date;
+pub mod decode;
+pub mod devices;
+pub mod discover;
 pub mod pair;
 pub mod promiscuous;
 pub mod run;
+pub mod satellite;
+pub mod schedule;
 pub mod sensors;
 pub mod sonde;
+pub mod stats;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -28,6 +39,15 @@ pub struct Cli {
     )]
     pub config: String,
 
+    /// Override a config value, e.g. `home_assistant.port=8123`, after
+    /// FRISQUET_ environment variables
+    #[arg(global = true, long, value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    /// Device to use when several devices are paired with the same role
+    #[arg(global = true, long, value_name = "NAME")]
+    pub device: Option<String>,
+
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub debug: u8,
@@ -38,36 +58,239 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// pair with boiler
+    /// pair with boiler, same as `devices pair`
     Pair {
         #[arg(value_enum)]
         from: Entity,
+        /// device name, defaults to the role
+        #[arg(long)]
+        name: Option<String>,
+        /// replace an already paired device with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// upgrade the config file or export its schema, without reading it
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// manage paired devices
+    Devices {
+        #[command(subcommand)]
+        command: DevicesCommands,
     },
     /// get sensors
     Sensors,
-    /// get data1 - not decoded
+    /// get data1
     Data1,
-    /// get data2 - not decoded
+    /// get data2
     Data2,
-    /// get data3 - not decoded
+    /// get data3
     Data3,
-    /// get data4 - not decoded
+    /// get data4
     Data4,
     /// get date
     Date,
     /// dump connect messages
-    Promiscuous,
-    /// set area1 prog
-    Area1,
+    Promiscuous {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// decode hex frames, from arguments, a file or stdin
+    Decode {
+        frames: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// read frames from a file, one per line
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<String>,
+    },
+    /// report constant and changing bytes of captured frames
+    Analyze {
+        /// read frames from a capture file instead of listening
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<String>,
+        /// number of frames to capture
+        #[arg(short = 'n', long, default_value_t = 100)]
+        count: usize,
+        /// save captured frames, to analyze them later with --file
+        #[arg(short, long, value_name = "FILE")]
+        save: Option<String>,
+    },
+    /// list devices seen on a network, before pairing
+    Discover {
+        /// network id, hex encoded, defaults to broadcast ffffffff
+        network_id: Option<String>,
+        /// seconds to listen
+        #[arg(short, long, default_value_t = 300)]
+        duration: u64,
+    },
+    /// report per device request latency, retransmissions and unanswered requests
+    Stats {
+        /// seconds between reports
+        #[arg(short, long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// seconds after which a request is unanswered
+        #[arg(short, long, default_value_t = 15)]
+        timeout: u64,
+    },
+    /// set area1 prog, or change mode, boost or override
+    Area1 {
+        /// keep the change in the state file
+        #[arg(long, global = true)]
+        save: bool,
+        #[command(subcommand)]
+        command: Option<Area1Commands>,
+    },
+    /// check and display zone schedules
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+    /// list heating events of the configured calendar
+    Calendar {
+        #[command(subcommand)]
+        command: CalendarCommands,
+    },
     /// set exterior temp
     Sonde {
         /// exterior temperature C
-        temp: f32,
+        temp: Celsius,
+    },
+    /// set zone ambient temperature and setpoint, as a satellite
+    Satellite {
+        /// zone 1 to 3
+        zone: u8,
+        /// ambient temperature C
+        ambient: Celsius,
+        /// setpoint C
+        setpoint: Celsius,
     },
     /// daemon mode
     Run,
 }
 
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// validate zones and print their week, current slot highlighted
+    Show,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// upgrade the config file to the current version, with a backup
+    Migrate {
+        /// print the upgraded file instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// print the JSON Schema of the config file
+    Schema {
+        /// write the schema to a file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CalendarCommands {
+    /// read the calendar and print its events, active ones highlighted
+    Show,
+}
+
+#[derive(Subcommand)]
+enum Area1Commands {
+    /// set mode
+    Mode {
+        #[arg(value_enum)]
+        mode: AreaMode,
+    },
+    /// turn boost on or off
+    Boost {
+        #[arg(value_enum)]
+        state: Switch,
+    },
+    /// override the schedule until its next change, or a given time
+    Override {
+        #[arg(value_enum)]
+        mode: OverrideMode,
+        /// end of the override, reverted by `run`, implies --save
+        #[arg(long, value_name = "HH:MM")]
+        until: Option<String>,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum AreaMode {
+    Auto,
+    Comfort,
+    Reduced,
+    Frost,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Switch {
+    On,
+    Off,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OverrideMode {
+    Comfort,
+    Reduced,
+    None,
+}
+
+/// config value of a command line enum, e.g. `comfort`
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map_or(String::new(), |v| v.get_name().into())
+}
+
+impl From<&Area1Commands> for area1::Patch {
+    fn from(command: &Area1Commands) -> area1::Patch {
+        match command {
+            Area1Commands::Mode { mode } => area1::Patch::Mode(value_name(*mode)),
+            Area1Commands::Boost { state } => area1::Patch::Boost(*state == Switch::On),
+            Area1Commands::Override { mode, until } => area1::Patch::Override {
+                mode: value_name(*mode),
+                until: until.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum DevicesCommands {
+    /// pair a new device with boiler
+    Pair {
+        #[arg(value_enum)]
+        from: Entity,
+        /// device name, defaults to the role
+        #[arg(long)]
+        name: Option<String>,
+        /// replace an already paired device with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// forget a paired device
+    Unpair { name: String },
+    /// list paired devices
+    List,
+    /// rename a paired device
+    Rename { from: String, to: String },
+}
+
+/// Output of decoded frames
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// colored text, for a terminal
+    Text,
+    /// one JSON object per line
+    Json,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum Entity {
     Connect,
@@ -77,28 +300,107 @@ pub enum Entity {
     Sonde,
 }
 
+impl From<Entity> for Role {
+    fn from(entity: Entity) -> Role {
+        match entity {
+            Entity::Connect => Role::Connect,
+            Entity::SatelliteZ1 => Role::SatelliteZ1,
+            Entity::SatelliteZ2 => Role::SatelliteZ2,
+            Entity::SatelliteZ3 => Role::SatelliteZ3,
+            Entity::Sonde => Role::Sonde,
+        }
+    }
+}
+
 pub fn parse() -> Cli {
     Cli::parse()
 }
 
 impl Cli {
-    pub fn run(
-        &self,
-        rf: &mut Box<dyn RFClient>,
-        config: &mut Config,
-    ) -> Result<(), Box<dyn Error>> {
-        match self.command {
-            Some(Commands::Pair { from }) => pair::run(rf, from, config),
-            Some(Commands::Sensors) => sensors::run(rf, config),
-            Some(Commands::Date) => date::run(rf, config),
-            Some(Commands::Promiscuous) => promiscuous::run(rf, config),
-            Some(Commands::Area1) => area1::run(rf, config),
-            Some(Commands::Data1) => data1::run(rf, config),
-            Some(Commands::Data2) => data2::run(rf, config),
-            Some(Commands::Data3) => data3::run(rf, config),
-            Some(Commands::Data4) => data4::run(rf, config),
-            Some(Commands::Sonde { temp }) => sonde::run(rf, temp, config),
-            Some(Commands::Run) => run::run(rf, config),
+    /// Run commands working on the config file itself, which may not be
+    /// readable by this version, `None` for the other commands.
+    pub fn run_without_config(&self) -> Option<Result<(), Box<dyn Error>>> {
+        match &self.command {
+            Some(Commands::Config {
+                command: ConfigCommands::Migrate { dry_run },
+            }) => Some(config::migrate(&self.config, *dry_run)),
+            Some(Commands::Config {
+                command: ConfigCommands::Schema { output },
+            }) => Some(config::schema(output)),
+            _ => None,
+        }
+    }
+
+    pub fn run(&self, config: &mut Config) -> Result<(), Box<dyn Error>> {
+        config.selected = self.device.clone();
+
+        // the radio is only opened by commands using it
+        match &self.command {
+            Some(Commands::Pair { from, name, force })
+            | Some(Commands::Devices {
+                command: DevicesCommands::Pair { from, name, force },
+            }) => pair::run(&mut rf::new(config)?, *from, name, *force, config),
+            Some(Commands::Devices {
+                command: DevicesCommands::Unpair { name },
+            }) => devices::unpair(name, config),
+            Some(Commands::Devices {
+                command: DevicesCommands::List,
+            }) => devices::list(config),
+            Some(Commands::Devices {
+                command: DevicesCommands::Rename { from, to },
+            }) => devices::rename(from, to, config),
+            Some(Commands::Config { .. }) => match self.run_without_config() {
+                Some(result) => result,
+                None => Ok(()),
+            },
+            Some(Commands::Sensors) => sensors::run(&mut rf::new(config)?, config),
+            Some(Commands::Date) => date::run(&mut rf::new(config)?, config),
+            Some(Commands::Promiscuous { format }) => {
+                promiscuous::run(&mut rf::new(config)?, *format, config)
+            }
+            Some(Commands::Decode {
+                frames,
+                format,
+                file,
+            }) => decode::run(frames, *format, file),
+            Some(Commands::Analyze {
+                file: Some(file), ..
+            }) => analyze::read(file),
+            Some(Commands::Analyze {
+                file: None,
+                count,
+                save,
+            }) => analyze::run(&mut rf::new(config)?, *count, save, config),
+            Some(Commands::Discover {
+                network_id,
+                duration,
+            }) => discover::run(&mut rf::new(config)?, network_id, *duration),
+            Some(Commands::Stats { interval, timeout }) => {
+                stats::run(&mut rf::new(config)?, *interval, *timeout, config)
+            }
+            Some(Commands::Area1 { save, command }) => area1::run(
+                &mut rf::new(config)?,
+                command.as_ref().map(area1::Patch::from),
+                *save,
+                config,
+            ),
+            Some(Commands::Data1) => data1::run(&mut rf::new(config)?, config),
+            Some(Commands::Data2) => data2::run(&mut rf::new(config)?, config),
+            Some(Commands::Data3) => data3::run(&mut rf::new(config)?, config),
+            Some(Commands::Data4) => data4::run(&mut rf::new(config)?, config),
+            Some(Commands::Schedule {
+                command: ScheduleCommands::Show,
+            }) => schedule::show(config),
+            Some(Commands::Calendar {
+                command: CalendarCommands::Show,
+            }) => calendar::show(config),
+            Some(Commands::Sonde { temp }) => sonde::run(&mut rf::new(config)?, *temp, config),
+            Some(Commands::Satellite {
+                zone,
+                ambient,
+                setpoint,
+            }) => satellite::run(&mut rf::new(config)?, *zone, *ambient, *setpoint, config),
+            Some(Commands::Run) => run::run(&mut rf::new(config)?, config),
             None => {
                 println!("main");
                 Ok(())
@@ -1,15 +1,26 @@
 use crate::cmd::Entity;
 use std::error::Error;
 
-use crate::config::{self, Config};
+use crate::config::{self, Config, Role};
 use crate::connect::pair::connect_association;
 use crate::rf::RFClient;
 
 pub fn run(
     rf: &mut Box<dyn RFClient>,
     from: Entity,
+    name: &Option<String>,
+    force: bool,
     config: &mut Config,
 ) -> Result<(), Box<dyn Error>> {
+    let role = Role::from(from);
+    let name = name.clone().unwrap_or(role.to_string());
+    if config.devices.contains_key(&name) && !force {
+        Err(format!(
+            "device {} is already paired, use --force to replace it or --name to add another one",
+            name
+        ))?;
+    }
+
     let from_addr = match from {
         Entity::Connect => 0x7e,
         Entity::Sonde => 0x20,
@@ -24,27 +35,20 @@ pub fn run(
         ass.network_id, ass.association_id
     );
 
-    match from {
-        Entity::Connect => {
-            config.frisquet = Some(config::Frisquet {
-                network_id: Some(ass.network_id),
-                association_id: Some(ass.association_id),
-                request_id: Some(ass.request_id),
-                send_init: Some(false),
-            })
-        }
-        Entity::Sonde => {
-            config.sonde = Some(config::Frisquet {
-                network_id: Some(ass.network_id),
-                association_id: Some(ass.association_id),
-                request_id: Some(ass.request_id),
-                send_init: Some(true),
-            })
-        }
-        Entity::SatelliteZ1 => (),
-        Entity::SatelliteZ2 => (),
-        Entity::SatelliteZ3 => (),
-    };
-
-    Ok(())
+    config.update(|config| {
+        config.devices.insert(
+            name,
+            config::Device {
+                role,
+                association: config::Frisquet {
+                    network_id: Some(ass.network_id),
+                    association_id: Some(ass.association_id),
+                    request_id: Some(ass.request_id),
+                    send_init: Some(role == Role::Sonde),
+                    ..Default::default()
+                },
+            },
+        );
+        Ok(())
+    })
 }
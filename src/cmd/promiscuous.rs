@@ -1,11 +1,19 @@
 use std::error::Error;
 
+use crate::cmd::decode::print_frame;
+use crate::cmd::Format;
 use crate::config::Config;
 use crate::connect::promiscuous::connect_promiscuous;
 use crate::rf::RFClient;
 
-pub fn run(rf: &mut Box<dyn RFClient>, config: &mut Config) -> Result<(), Box<dyn Error>> {
-    connect_promiscuous(rf, config.frisquet()?)?;
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    format: Format,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    connect_promiscuous(rf, config.frisquet()?, |raw, frame| {
+        print_frame(format, raw, frame)
+    })?;
 
     Ok(())
 }
@@ -1,25 +1,252 @@
 use chrono::Duration;
 use std::error::Error;
 use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use crate::config::Config;
+use crate::cmd::area1::revert_expired_override;
+use crate::cmd::calendar::CalendarSync;
+use crate::config::state::OutdoorState;
+use crate::config::{Config, ConfigError};
+use crate::connect::area::try_connect_area1;
+use crate::connect::satellite::send_zone_temperature;
 use crate::connect::sonde::{send_init, send_temperature};
-use crate::datasource::externaltemperature::homeassistant;
+use crate::connect::temperature::Celsius;
+use crate::datasource::externaltemperature::{self, ExternalTemperatureSource};
+use crate::emulation::sonde::SondeEmulator;
 use crate::rf::RFClient;
 
+/// Seconds between checks of the config file while waiting for a cycle.
+const RELOAD_POLL: i64 = 10;
+
 pub fn run(rf: &mut Box<dyn RFClient>, config: &mut Config) -> Result<(), Box<dyn Error>> {
-    let sonde_config = config.sonde()?;
-    if sonde_config.send_init.unwrap_or(false) {
-        let (_meta, _sensor) = send_init(rf, sonde_config)?;
-        sonde_config.send_init = Some(false);
+    config.validate()?;
+    let satellites = config.satellites.as_ref().map_or(0, |s| s.len());
+    let area_override = config
+        .area1
+        .as_ref()
+        .is_some_and(|a| a.override_until.is_some());
+    if !config.has_sonde() && satellites == 0 && !area_override && config.calendar.is_none() {
+        Err("nothing to run: configure sonde, satellites or calendar")?;
+    }
+
+    if config.has_sonde() && config.sonde()?.send_init.unwrap_or(false) {
+        let (_meta, _sensor) = send_init(rf, config.sonde()?)?;
+        config.update(|config| {
+            config.sonde()?.send_init = Some(false);
+            Ok::<_, ConfigError>(())
+        })?;
     }
-    config.write()?;
+
+    let mut sources = Sources::new(config)?;
+    let mut calendar = config.calendar.as_ref().map(CalendarSync::new);
+    let mut sonde = SondeEmulator::new(config.outdoor.as_ref().unwrap_or(&Default::default()))?;
+    restore_outdoor(&mut sonde, config);
+    let mut modified = config.modified()?;
 
     loop {
-        let temperature = homeassistant::get_ha_client(config.home_assistant()?)?;
-        println!("Set temperature to: {:.1}", temperature);
-        let (_meta, _sensor) = send_temperature(rf, config.sonde()?, temperature)?;
+        // devices and area1 changed by other commands
+        config.load_state()?;
+
+        if let Ok(time) = config.modified() {
+            if time != modified {
+                modified = time;
+                reload(rf, config, &mut sonde, &mut sources, &mut calendar);
+            }
+        }
+
+        if let Some(outdoor) = &mut sources.outdoor {
+            let reading = outdoor.get();
+            if let Err(e) = &reading {
+                println!("Outdoor temperature unavailable: {}", e);
+            }
+            let fresh = reading.is_ok();
+            // a cached value keeps the time it was read at
+            let age = match fresh {
+                true => outdoor.age(),
+                false => std::time::Duration::ZERO,
+            };
+            let now = Instant::now();
+            match sonde.update(reading, now.checked_sub(age).unwrap_or(now)) {
+                Some(temperature) => {
+                    if fresh {
+                        let last = OutdoorState {
+                            value: temperature,
+                            at: unix_now().saturating_sub(age.as_secs()),
+                        };
+                        config.update(|config| {
+                            config.last_outdoor = Some(last);
+                            Ok::<_, ConfigError>(())
+                        })?;
+                    }
+                    println!("Set temperature to: {:.1}", temperature);
+                    if let Err(e) = send_temperature(rf, config.sonde()?, Celsius::new(temperature))
+                    {
+                        println!("Outdoor temperature not sent: {}", e);
+                    }
+                }
+                None => println!("Outdoor temperature is stale, nothing sent"),
+            }
+        }
+
+        let satellites: Vec<_> = config
+            .satellites
+            .iter()
+            .flatten()
+            .map(|s| (s.zone, s.setpoint))
+            .collect();
+        for ((zone, setpoint), indoor) in satellites.into_iter().zip(&mut sources.indoor) {
+            let ambient = match indoor.get() {
+                Ok(ambient) => Celsius::new(ambient),
+                Err(e) => {
+                    println!("Zone {} ambient temperature unavailable: {}", zone, e);
+                    continue;
+                }
+            };
+            println!(
+                "Set zone {} ambient to: {}, setpoint: {}",
+                zone, ambient, setpoint
+            );
+            // unpaired meanwhile with `devices unpair`
+            let satellite = match config.satellite(zone) {
+                Ok(satellite) => satellite,
+                Err(e) => {
+                    println!("Zone {} temperature not sent: {}", zone, e);
+                    continue;
+                }
+            };
+            if let Err(e) = send_zone_temperature(rf, satellite, zone, ambient, setpoint) {
+                println!("Zone {} temperature not sent: {}", zone, e);
+            }
+        }
+
+        if let Some(calendar) = &mut calendar {
+            if let Err(e) = calendar.apply(rf, config) {
+                println!("Calendar not applied: {}", e);
+            }
+        }
 
-        thread::sleep(Duration::minutes(3).to_std()?);
+        if let Err(e) = revert_expired_override(rf, config) {
+            println!("Failed to revert area1 override: {}", e);
+        }
+
+        wait_cycle(config, modified)?;
+    }
+}
+
+/// Temperature sources of the sonde and satellites, as configured.
+struct Sources {
+    outdoor: Option<Box<dyn ExternalTemperatureSource>>,
+    indoor: Vec<Box<dyn ExternalTemperatureSource>>,
+}
+
+impl Sources {
+    fn new(config: &Config) -> Result<Sources, Box<dyn Error>> {
+        let outdoor = match config.has_sonde() {
+            true => Some(externaltemperature::outdoor(config)?),
+            false => None,
+        };
+        let indoor = config
+            .satellites
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, satellite)| externaltemperature::indoor(config, index, satellite))
+            .collect::<Result<_, _>>()?;
+        Ok(Sources { outdoor, indoor })
     }
 }
+
+/// Seed the emulator with the last good outdoor temperature, sent until it
+/// goes stale when the source is down after a restart.
+fn restore_outdoor(sonde: &mut SondeEmulator, config: &Config) {
+    if let Some(last) = &config.last_outdoor {
+        let age = std::time::Duration::from_secs(unix_now().saturating_sub(last.at));
+        sonde.restore(last.value, age, Instant::now());
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Wait for the next cycle, or less when the config file changes.
+fn wait_cycle(config: &Config, modified: SystemTime) -> Result<(), Box<dyn Error>> {
+    for _ in 0..(Duration::minutes(3).num_seconds() / RELOAD_POLL) {
+        thread::sleep(Duration::seconds(RELOAD_POLL).to_std()?);
+        if config.modified().is_ok_and(|time| time != modified) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the config with the edited file and push what changed, an invalid
+/// file is logged and the current config kept.
+fn reload(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut Config,
+    sonde: &mut SondeEmulator,
+    sources: &mut Sources,
+    calendar: &mut Option<CalendarSync>,
+) {
+    let new = match config.reload() {
+        Ok(new) => new,
+        Err(e) => {
+            println!("Config not reloaded, keeping the previous one: {}", e);
+            return;
+        }
+    };
+    let (emulator, new_sources) = match rebuild(config, &new) {
+        Ok(rebuilt) => rebuilt,
+        Err(e) => {
+            println!("Config not reloaded, keeping the previous one: {}", e);
+            return;
+        }
+    };
+    if let Some(emulator) = emulator {
+        *sonde = emulator;
+        restore_outdoor(sonde, config);
+    }
+    if let Some(new_sources) = new_sources {
+        *sources = new_sources;
+    }
+    println!("Config reloaded");
+
+    // the calendar writes the holiday again on its next cycle
+    if new.calendar != config.calendar {
+        *calendar = new.calendar.as_ref().map(CalendarSync::new);
+    }
+    let area_changed = new.area1 != config.area1;
+    *config = new;
+    if area_changed {
+        println!("Area1 changed, sending it");
+        if let Err(e) = try_connect_area1(rf, config) {
+            println!("Failed to send area1: {}", e);
+        }
+    }
+}
+
+/// Emulator and sources of `new`, for those whose settings changed.
+fn rebuild(
+    config: &Config,
+    new: &Config,
+) -> Result<(Option<SondeEmulator>, Option<Sources>), Box<dyn Error>> {
+    let emulator = match new.outdoor != config.outdoor {
+        true => Some(SondeEmulator::new(
+            new.outdoor.as_ref().unwrap_or(&Default::default()),
+        )?),
+        false => None,
+    };
+    let sources = match new.outdoor != config.outdoor
+        || new.home_assistant != config.home_assistant
+        || new.mqtt != config.mqtt
+        || new.satellites != config.satellites
+        || new.has_sonde() != config.has_sonde()
+    {
+        true => Some(Sources::new(new)?),
+        false => None,
+    };
+    Ok((emulator, sources))
+}
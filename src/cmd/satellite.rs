@@ -0,0 +1,19 @@
+use std::error::Error;
+
+use crate::config::Config;
+use crate::connect::satellite::send_zone_temperature;
+use crate::connect::temperature::Celsius;
+use crate::rf::RFClient;
+
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    zone: u8,
+    ambient: Celsius,
+    setpoint: Celsius,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    let (_meta, _reply) =
+        send_zone_temperature(rf, config.satellite(zone)?, zone, ambient, setpoint)?;
+
+    Ok(())
+}
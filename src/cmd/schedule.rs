@@ -0,0 +1,71 @@
+use chrono::{Datelike, Local, Timelike, Weekday};
+use colored::Colorize;
+use std::error::Error;
+
+use crate::config::schedule::{day_name, SLOTS, WEEK};
+use crate::config::{Area, Config};
+
+/// Validate the zone programs and print their week, without using the radio.
+pub fn show(config: &Config) -> Result<(), Box<dyn Error>> {
+    let zones: Vec<(&str, &Area)> = config.area1.iter().map(|a| ("area1", a)).collect();
+    if zones.is_empty() {
+        Err("no zone configured")?;
+    }
+
+    let now = Local::now();
+    let mut valid = true;
+    for (name, area) in zones {
+        println!("{}", format!("== {}", name).cyan());
+        if let Err(e) = area.validate() {
+            println!("{}", format!("invalid {}: {}", name, e).red());
+            valid = false;
+            continue;
+        }
+        println!(
+            "comfort {}, reduced {}, frost {}, mode {}, boost {}, override {}",
+            area.comfort, area.reduced, area.frost, area.mode, area.boost, area.r#override
+        );
+        print_week(
+            area,
+            now.weekday(),
+            now.hour() as u8 * 2 + now.minute() as u8 / 30,
+        )?;
+
+        let comfort = area.comfort_at(now.weekday(), now.hour() as u8, now.minute() as u8)?;
+        println!(
+            "now: {} {}, {}\n",
+            day_name(now.weekday()),
+            now.format("%H:%M"),
+            if comfort { "comfort" } else { "reduced" }
+        );
+    }
+
+    if !valid {
+        Err("invalid schedule")?;
+    }
+    Ok(())
+}
+
+/// One line per day, one character per 30 minutes slot: `#` comfort, `.`
+/// reduced, the current slot highlighted.
+fn print_week(area: &Area, today: Weekday, current: u8) -> Result<(), Box<dyn Error>> {
+    // two slots per hour
+    let hours: String = (0..24).step_by(2).map(|h| format!("{:<4}", h)).collect();
+    println!("{:10}{}", "", hours.trim_end());
+
+    for day in WEEK {
+        let bitmap = area.day(day)?;
+        let mut line = format!("{:10}", day_name(day));
+        for slot in 0..SLOTS {
+            let comfort = bitmap[(slot / 8) as usize] & (1 << (slot % 8)) != 0;
+            let c = if comfort { "#".green() } else { ".".normal() };
+            if day == today && slot == current {
+                line.push_str(&c.reversed().to_string());
+            } else {
+                line.push_str(&c.to_string());
+            }
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
@@ -1,21 +1,24 @@
 use std::error::Error;
 
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
 use crate::connect::sonde::{send_init, send_temperature};
+use crate::connect::temperature::Celsius;
 use crate::rf::RFClient;
 
 pub fn run(
     rf: &mut Box<dyn RFClient>,
-    temp: f32,
+    temp: Celsius,
     config: &mut Config,
 ) -> Result<(), Box<dyn Error>> {
-    let sonde_config = config.sonde()?;
-    if sonde_config.send_init.unwrap_or(false) {
-        let (_meta, _sensor) = send_init(rf, sonde_config)?;
-        sonde_config.send_init = Some(false);
+    if config.sonde()?.send_init.unwrap_or(false) {
+        let (_meta, _sensor) = send_init(rf, config.sonde()?)?;
+        config.update(|config| {
+            config.sonde()?.send_init = Some(false);
+            Ok::<_, ConfigError>(())
+        })?;
     }
 
-    let (_meta, _sensor) = send_temperature(rf, sonde_config, temp)?;
+    let (_meta, _sensor) = send_temperature(rf, config.sonde()?, temp)?;
 
     Ok(())
 }
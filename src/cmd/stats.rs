@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use deku::prelude::*;
+
+use crate::config::Config;
+use crate::connect::tracker::Tracker;
+use crate::connect::Metadata;
+use crate::rf::RFClient;
+
+/// Listen to the network and print per device request statistics every
+/// `interval` seconds.
+pub fn run(
+    rf: &mut Box<dyn RFClient>,
+    interval: u64,
+    timeout: u64,
+    config: &mut Config,
+) -> Result<(), Box<dyn Error>> {
+    rf.set_network_id(config.frisquet()?.network_id()?)?;
+
+    let interval = Duration::from_secs(interval);
+    let mut tracker = Tracker::new(Duration::from_secs(timeout));
+    let mut next_report = Instant::now() + interval;
+
+    loop {
+        let wait = next_report.saturating_duration_since(Instant::now());
+        match rf.recv_timeout(wait) {
+            Ok(frame) => match Metadata::from_bytes((&frame, 0)) {
+                Ok((_, meta)) => tracker.push(&meta, Instant::now()),
+                Err(e) => println!("meta err: {} - {}", e, hex::encode(&frame)),
+            },
+            Err(e) if e.is_timeout() => {}
+            Err(e) => Err(e)?,
+        }
+
+        if Instant::now() >= next_report {
+            tracker.expire(Instant::now());
+            println!("{}\n", tracker);
+            next_report += interval;
+        }
+    }
+}
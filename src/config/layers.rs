@@ -0,0 +1,219 @@
+use std::fs::read_to_string;
+use toml::{Table, Value};
+
+use super::ConfigError;
+
+/// Prefix of environment variables overriding config values.
+pub const ENV_PREFIX: &str = "FRISQUET_";
+
+/// Config file values, overridden by `FRISQUET_` environment variables then
+/// by `key=value` command line values. `<key>_file` values are then replaced
+/// by a `<key>` value read from that file, for secrets. Missing values take
+/// their defaults on deserialization.
+pub fn merge(
+    content: &str,
+    env: impl Iterator<Item = (String, String)>,
+    sets: &[String],
+) -> Result<Table, ConfigError> {
+    let mut table: Table = toml::from_str(content)?;
+
+    for (name, value) in env {
+        if let Some(path) = env_path(&name) {
+            set(&mut table, &path, &value)
+                .map_err(|e| ConfigError::new(&format!("{}: {}", name, e)))?;
+        }
+    }
+    for arg in sets {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| ConfigError::new(&format!("expected key=value: {}", arg)))?;
+        let path: Vec<String> = key.trim().split('.').map(String::from).collect();
+        set(&mut table, &path, value).map_err(|e| ConfigError::new(&format!("{}: {}", key, e)))?;
+    }
+
+    read_files(&mut table)?;
+    Ok(table)
+}
+
+/// Key path of a variable, `__` separating levels:
+/// `FRISQUET_HOME_ASSISTANT__TOKEN` is `home_assistant.token` and
+/// `FRISQUET_SATELLITES__0__SETPOINT` the setpoint of the first satellite.
+fn env_path(name: &str) -> Option<Vec<String>> {
+    let key = name.strip_prefix(ENV_PREFIX)?;
+    if key.is_empty() {
+        return None;
+    }
+    Some(key.to_lowercase().split("__").map(String::from).collect())
+}
+
+/// Set the value at `path`, creating missing tables. Values replacing a
+/// string, not valid TOML or numbers not written as TOML would, e.g. `1.50`,
+/// are strings.
+fn set(table: &mut Table, path: &[String], raw: &str) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("empty key")?;
+    let mut current = table;
+    let mut keys = parents.iter();
+    while let Some(key) = keys.next() {
+        let mut value = current
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        if let Value::Array(items) = value {
+            // array of tables, indexed by the next key
+            let index: usize = keys
+                .next()
+                .and_then(|index| index.parse().ok())
+                .ok_or(format!("{} is a list, expected an index", key))?;
+            value = items
+                .get_mut(index)
+                .ok_or(format!("no {}[{}]", key, index))?;
+        }
+        current = match value {
+            Value::Table(t) => t,
+            _ => return Err(format!("{} is not a table", key)),
+        };
+    }
+
+    let value = match current.get(last) {
+        Some(Value::String(_)) => Value::String(raw.into()),
+        _ => parse_value(raw).unwrap_or(Value::String(raw.into())),
+    };
+    current.insert(last.clone(), value);
+    Ok(())
+}
+
+fn parse_value(raw: &str) -> Option<Value> {
+    let mut table: Table = toml::from_str(&format!("value = {}", raw)).ok()?;
+    let value = table.remove("value")?;
+    // scalars only when lossless, a password stays as typed
+    match value {
+        Value::Integer(_) | Value::Float(_) | Value::Boolean(_) if value.to_string() != raw => None,
+        value => Some(value),
+    }
+}
+
+/// Replace `<key>_file` strings by `<key>` with the file content.
+fn read_files(table: &mut Table) -> Result<(), ConfigError> {
+    let files: Vec<String> = table
+        .iter()
+        .filter(|(key, value)| key.ends_with("_file") && value.is_str())
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in files {
+        let name = key.trim_end_matches("_file").to_string();
+        if table.contains_key(&name) {
+            Err(ConfigError::new(&format!("both {} and {} set", name, key)))?;
+        }
+        if let Some(Value::String(path)) = table.remove(&key) {
+            let content = read_to_string(&path)
+                .map_err(|e| ConfigError::new(&format!("{} {}: {}", key, path, e)))?;
+            table.insert(name, Value::String(content.trim_end().into()));
+        }
+    }
+
+    for (_, value) in table.iter_mut() {
+        match value {
+            Value::Table(t) => read_files(t)?,
+            Value::Array(items) => {
+                for item in items {
+                    if let Value::Table(t) = item {
+                        read_files(t)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{remove_file, write};
+
+    const CONTENT: &str = r#"
+        [home_assistant]
+        host = "http://ha:8123"
+        port = 8123
+        token = "secret"
+
+        [[satellites]]
+        zone = 1
+        setpoint = 20.0
+    "#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_env_and_sets() {
+        let table = merge(
+            CONTENT,
+            env(&[
+                ("FRISQUET_HOME_ASSISTANT__TOKEN", "1234"),
+                ("FRISQUET_SATELLITES__0__SETPOINT", "21.5"),
+                ("FRISQUET_OUTDOOR__MAX_STEP", "1.5"),
+                ("FRISQUET_MQTT__PASSWORD", "123456"),
+                ("FRISQUET_MQTT__USERNAME", "1.50"),
+                ("HOME", "/root"),
+            ]),
+            &["home_assistant.port=8124".into()],
+        )
+        .unwrap();
+
+        let ha = table["home_assistant"].as_table().unwrap();
+        // strings stay strings
+        assert_eq!(ha["token"].as_str(), Some("1234"));
+        assert_eq!(ha["port"].as_integer(), Some(8124));
+        assert_eq!(table["satellites"][0]["setpoint"].as_float(), Some(21.5));
+        assert_eq!(table["outdoor"]["max_step"].as_float(), Some(1.5));
+        // numbers the config reads back as strings
+        assert_eq!(table["mqtt"]["password"].as_integer(), Some(123456));
+        assert_eq!(table["mqtt"]["username"].as_str(), Some("1.50"));
+        assert!(!table.contains_key("home"));
+    }
+
+    #[test]
+    fn test_numeric_secret() {
+        let content = r#"
+            [mqtt]
+            broker = "tcp://localhost:1883"
+            client_id = "frisquet"
+            cmd_topic = "cmd"
+            lst_topic = "lst"
+        "#;
+        let table = merge(content, env(&[("FRISQUET_MQTT__PASSWORD", "123456")]), &[]).unwrap();
+        let config: crate::config::Config = table.try_into().unwrap();
+        assert_eq!(config.mqtt.unwrap().password.as_deref(), Some("123456"));
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        assert!(merge(CONTENT, env(&[("FRISQUET_SATELLITES__3__ZONE", "2")]), &[]).is_err());
+        assert!(merge(CONTENT, env(&[]), &["home_assistant.port".into()]).is_err());
+    }
+
+    #[test]
+    fn test_secret_file() {
+        let path = std::env::temp_dir().join(format!("frisquet-token-{}", std::process::id()));
+        write(&path, "from-file\n").unwrap();
+        let content = CONTENT.replace(
+            "token = \"secret\"",
+            &format!("token_file = {:?}", path.to_string_lossy()),
+        );
+
+        let table = merge(&content, env(&[]), &[]).unwrap();
+        assert_eq!(table["home_assistant"]["token"].as_str(), Some("from-file"));
+        assert!(merge(
+            CONTENT,
+            env(&[]),
+            &[format!("home_assistant.token_file={:?}", path)]
+        )
+        .is_err());
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+use toml_edit::{value, Array, Document, Item, Table};
+
+use super::schedule::{day_name, to_ranges, WEEK};
+use super::{to_mode_prog, ConfigError, Prog};
+
+/// Version of the config files written by this release.
+pub const VERSION: u32 = 2;
+
+type Migration = fn(&mut Document) -> Result<(), ConfigError>;
+
+/// Upgrades from version `n + 1` to `n + 2`.
+const MIGRATIONS: [Migration; (VERSION - 1) as usize] = [v1_to_v2];
+
+/// Version of a config file, 1 before versions were written.
+pub fn version(doc: &Document) -> Result<u32, ConfigError> {
+    match doc.get("version") {
+        None => Ok(1),
+        Some(item) => item
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(invalid_version)
+            .and_then(|v| check(v).map(|()| v)),
+    }
+}
+
+fn invalid_version() -> ConfigError {
+    ConfigError::new("invalid version, expected a number from 1")
+}
+
+/// Refuse versions `migrate` can't handle: 0 and files from a newer release.
+pub fn check(version: u32) -> Result<(), ConfigError> {
+    if version == 0 {
+        return Err(invalid_version());
+    }
+    if version > VERSION {
+        return Err(ConfigError::new(&format!(
+            "config version {} is newer than the supported version {}",
+            version, VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Upgrade a config file to the current version, keeping comments and
+/// layout. `None` when it is already current.
+pub fn migrate(content: &str) -> Result<Option<(u32, String)>, ConfigError> {
+    let mut doc: Document = content.parse()?;
+    let from = version(&doc)?;
+    if from == VERSION {
+        return Ok(None);
+    }
+
+    for migration in &MIGRATIONS[(from - 1) as usize..] {
+        migration(&mut doc)?;
+    }
+    doc["version"] = value(VERSION as i64);
+    Ok(Some((from, doc.to_string())))
+}
+
+/// Legacy association sections move to `[devices.<role>]`, per day
+/// `[[area1.<day>]]` tables to `[area1.schedule]`.
+fn v1_to_v2(doc: &mut Document) -> Result<(), ConfigError> {
+    let legacy = [
+        ("frisquet", "connect"),
+        ("sonde", "sonde"),
+        ("satellite_z1", "satellite-z1"),
+        ("satellite_z2", "satellite-z2"),
+        ("satellite_z3", "satellite-z3"),
+    ];
+    for (section, role) in legacy {
+        let Some(Item::Table(mut association)) = doc.remove(section) else {
+            continue;
+        };
+        let devices = doc
+            .entry("devices")
+            .or_insert_with(|| {
+                let mut devices = Table::new();
+                devices.set_implicit(true);
+                Item::Table(devices)
+            })
+            .as_table_mut()
+            .ok_or_else(|| ConfigError::new("devices is not a table"))?;
+        // paired devices win, as when reading
+        if !devices.contains_key(role) {
+            association.insert("role", value(role));
+            association.sort_values_by(|k1, _, k2, _| (k2 == "role").cmp(&(k1 == "role")));
+            devices.insert(role, Item::Table(association));
+        }
+    }
+
+    let Some(area) = doc.get_mut("area1").and_then(Item::as_table_mut) else {
+        return Ok(());
+    };
+    let mut days = vec![];
+    for day in WEEK {
+        let name = day_name(day);
+        let progs = match area.remove(name) {
+            Some(Item::ArrayOfTables(progs)) => progs,
+            Some(_) => return Err(ConfigError::new(&format!("area1.{} is not a list", name))),
+            None => {
+                // no comfort range that day
+                days.push((&name[0..3], Array::new()));
+                continue;
+            }
+        };
+        let progs = progs
+            .iter()
+            .map(|prog| {
+                let field = |key| {
+                    prog.get(key)
+                        .and_then(Item::as_str)
+                        .map(String::from)
+                        .ok_or_else(|| {
+                            ConfigError::new(&format!("area1.{}: missing {}", name, key))
+                        })
+                };
+                Ok(Prog {
+                    timeframe: field("timeframe")?,
+                    mode: field("mode")?,
+                })
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+        days.push((
+            &name[0..3],
+            to_ranges(&to_mode_prog(&progs)?).into_iter().collect(),
+        ));
+    }
+    if days.iter().all(|(_, ranges)| ranges.is_empty()) {
+        return Ok(());
+    }
+    if area.contains_key("schedule") {
+        return Err(ConfigError::new(
+            "area1 has both a schedule and per day tables",
+        ));
+    }
+    let mut schedule = Table::new();
+    for (day, ranges) in days {
+        schedule.insert(day, value(ranges));
+    }
+    area.insert("schedule", Item::Table(schedule));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const V1: &str = r#"# my boiler
+[frisquet]
+network_id = "12345678"
+association_id = "01"
+request_id = "04"
+
+[area1]
+comfort = 21.5
+reduced = 19.0
+frost = 8.5
+mode = "auto"
+boost = false
+override = "none"
+
+# week days
+[[area1.monday]]
+timeframe = "06:30-23:30"
+mode = "comfort"
+
+[[area1.saturday]]
+timeframe = "00:00-01:00"
+mode = "comfort"
+
+[[area1.saturday]]
+timeframe = "06:00-24:00"
+mode = "comfort"
+"#;
+
+    #[test]
+    fn test_v1_to_v2() {
+        let (from, content) = migrate(V1).unwrap().unwrap();
+        assert_eq!(from, 1);
+        assert!(content.contains("# my boiler\n[devices.connect]"));
+
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.version, Some(VERSION));
+        assert_eq!(config.devices["connect"].association.request_id, Some(0x04));
+        let area = config.area1.unwrap();
+        assert!(area.monday.is_empty());
+        area.validate().unwrap();
+        assert_eq!(
+            area.schedule["sat"],
+            super::super::schedule::DaySchedule::Ranges(vec![
+                "00:00-01:00".into(),
+                "06:00-24:00".into()
+            ])
+        );
+
+        // nothing left to do
+        assert_eq!(migrate(&content).unwrap(), None);
+    }
+
+    #[test]
+    fn test_newer_version() {
+        assert!(migrate(&format!("version = {}", VERSION + 1)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        assert!(migrate("version = 0").is_err());
+        assert!(migrate("version = -1").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        // what config::read is refused with, as migrate
+        for version in [0, VERSION + 1] {
+            let content = format!("version = {}", version);
+            assert_eq!(
+                check(version).unwrap_err().to_string(),
+                migrate(&content).unwrap_err().to_string()
+            );
+        }
+        assert!((1..=VERSION).all(|version| check(version).is_ok()));
+    }
+}
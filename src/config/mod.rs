@@ -0,0 +1,889 @@
+use chrono::{NaiveDateTime, Weekday};
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fmt::Debug;
+use std::fs::{metadata, read_to_string};
+use std::path::Path;
+use std::time::SystemTime;
+use std::usize;
+use toml;
+
+use crate::connect::temperature::{Celsius, Setpoint};
+use schedule::DaySchedule;
+use state::{AreaState, OutdoorState, State};
+
+pub mod layers;
+pub mod migrate;
+pub mod schedule;
+pub mod state;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Config {
+    /// schema version, see `config migrate`
+    pub version: Option<u32>,
+
+    // legacy associations, moved to devices on read
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
+    frisquet: Option<Frisquet>,
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
+    sonde: Option<Frisquet>,
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
+    satellite_z1: Option<Frisquet>,
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
+    satellite_z2: Option<Frisquet>,
+    #[serde(skip_serializing)]
+    #[schemars(skip)]
+    satellite_z3: Option<Frisquet>,
+
+    /// paired devices, moved to the state file on first write
+    #[serde(default, skip_serializing)]
+    #[schemars(with = "Option<BTreeMap<String, Device>>")]
+    pub devices: BTreeMap<String, Device>,
+    /// state file, defaults to `<config>.state.toml` next to the config
+    pub state: Option<String>,
+    pub home_assistant: Option<HAConfig>,
+    pub outdoor: Option<Outdoor>,
+    pub satellites: Option<Vec<Satellite>>,
+
+    pub serial: Option<Serial>,
+    pub mqtt: Option<MQTT>,
+    pub area1: Option<Area>,
+    pub calendar: Option<CalendarConfig>,
+
+    /// area1 as configured, before state changes
+    #[serde(skip)]
+    base_area1: Option<Area>,
+    #[serde(skip)]
+    state_path: String,
+    /// last good outdoor temperature, kept in the state file
+    #[serde(skip)]
+    pub last_outdoor: Option<OutdoorState>,
+    #[serde(skip)]
+    path: String,
+    #[serde(skip)]
+    sets: Vec<String>,
+    /// device to use when several devices have the same role
+    #[serde(skip)]
+    pub selected: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    Connect,
+    Sonde,
+    SatelliteZ1,
+    SatelliteZ2,
+    SatelliteZ3,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Device {
+    pub role: Role,
+    #[serde(flatten)]
+    pub association: Frisquet,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct Frisquet {
+    pub send_init: Option<bool>,
+    #[serde(
+        default,
+        serialize_with = "slice_as_hex",
+        deserialize_with = "slice_from_hex"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub network_id: Option<[u8; 4]>,
+    #[serde(
+        default,
+        serialize_with = "u8_as_hex",
+        deserialize_with = "u8_from_hex"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub association_id: Option<u8>,
+    #[serde(
+        default,
+        serialize_with = "u8_as_hex",
+        deserialize_with = "u8_from_hex"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub request_id: Option<u8>,
+    /// state file and device name, request ids are shared through it
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub(crate) state: Option<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct HAConfig {
+    pub host: String,
+    pub port: u16,
+    #[serde(deserialize_with = "string_from_scalar")]
+    pub token: String,
+    pub entity_id: String,
+    /// attribute path, e.g. `temperature` or `forecast.0.temperature`, the
+    /// entity state when unset
+    pub temperature_field: Option<String>,
+    /// request timeout in seconds, defaults to 10
+    pub timeout: Option<u64>,
+}
+
+/// Where a temperature is read from, for the sonde and the satellites.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq)]
+pub struct TemperatureSource {
+    /// homeassistant (default), mqtt, onewire, openmeteo, file, command or
+    /// static, set in the table of that name
+    pub source: Option<String>,
+    /// sources tried in order or combined, instead of `source`
+    pub sources: Option<Vec<String>>,
+    /// first (default) source with a value, median or mean of all of them
+    pub combine: Option<String>,
+    /// oldest value in seconds used from a source, also its last value once it
+    /// fails, by source name. Unset, any value it returns is used, and none
+    /// once it fails
+    pub max_age: Option<BTreeMap<String, u64>>,
+    /// entity read by the homeassistant source, `[home_assistant]` when unset
+    pub homeassistant: Option<HAConfig>,
+    pub mqtt: Option<MqttSource>,
+    pub onewire: Option<OneWireSource>,
+    pub openmeteo: Option<OpenMeteoSource>,
+    pub file: Option<FileSource>,
+    pub command: Option<CommandSource>,
+    pub r#static: Option<StaticSource>,
+}
+
+/// Sonde emulation settings, temperatures in °C and durations in seconds.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq)]
+pub struct Outdoor {
+    #[serde(flatten)]
+    pub input: TemperatureSource,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// maximum change between two sent values
+    pub max_step: Option<f32>,
+    /// none, ema (with alpha) or mean (with window)
+    pub filter: Option<String>,
+    pub alpha: Option<f32>,
+    pub window: Option<usize>,
+    /// keep sending the last good value this long when the source fails
+    pub stale_after: Option<u64>,
+}
+
+/// Temperature published on an MQTT topic, the broker and credentials of
+/// `[mqtt]` unless `broker` is set.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct MqttSource {
+    pub broker: Option<String>,
+    /// defaults to `frisquet-outdoor`
+    pub client_id: Option<String>,
+    #[serde(default, deserialize_with = "option_from_scalar")]
+    pub username: Option<String>,
+    #[serde(default, deserialize_with = "option_from_scalar")]
+    pub password: Option<String>,
+    pub topic: String,
+    /// JSON pointer, e.g. `/temperature`, the raw payload when unset
+    pub pointer: Option<String>,
+    /// seconds after which the last value is stale, defaults to 900
+    pub max_age: Option<u64>,
+}
+
+/// DS18B20 probe on the kernel 1-Wire bus, readings are `value * scale +
+/// offset` in °C.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct OneWireSource {
+    /// e.g. `28-0316a2794aff`, the only probe found when unset
+    pub device: Option<String>,
+    pub scale: Option<f32>,
+    pub offset: Option<f32>,
+}
+
+/// Current temperature at a location from an Open-Meteo compatible API.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct OpenMeteoSource {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// defaults to `https://api.open-meteo.com`
+    pub url: Option<String>,
+    /// minimum seconds between requests, defaults to 900
+    pub refresh: Option<u64>,
+    /// request timeout in seconds, defaults to 10
+    pub timeout: Option<u64>,
+}
+
+/// Number read from a file, `value * scale + offset` in °C.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct FileSource {
+    pub path: String,
+    pub scale: Option<f32>,
+    pub offset: Option<f32>,
+}
+
+/// Number printed by a `sh -c` command, `value * scale + offset` in °C.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct CommandSource {
+    pub command: String,
+    pub scale: Option<f32>,
+    pub offset: Option<f32>,
+    /// seconds before the command is killed, defaults to 5
+    pub timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct StaticSource {
+    pub value: f32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Satellite {
+    pub zone: u8,
+    pub setpoint: Celsius,
+    /// ambient temperature, read as the outdoor one
+    #[serde(deserialize_with = "indoor_source")]
+    pub indoor: TemperatureSource,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Serial {
+    pub port: String,
+    pub speed: u32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct MQTT {
+    pub broker: String,
+    pub client_id: String,
+    pub cmd_topic: String,
+    pub lst_topic: String,
+    #[serde(default, deserialize_with = "option_from_scalar")]
+    pub username: Option<String>,
+    /// or `password_file`, like any value
+    #[serde(default, deserialize_with = "option_from_scalar")]
+    pub password: Option<String>,
+}
+/// iCalendar events driving overrides and holidays, refreshed in seconds.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct CalendarConfig {
+    /// `.ics` file path or http(s) URL
+    pub source: String,
+    /// events tagged `<tag>:comfort`, `<tag>:reduced` or `<tag>:holiday`,
+    /// defaults to `heating`
+    pub tag: Option<String>,
+    pub refresh: Option<u64>,
+    /// send holiday events to the boiler, off by default as the holiday
+    /// write frame is not confirmed yet
+    pub write_holidays: Option<bool>,
+}
+
+pub const OVERRIDE_UNTIL_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Area {
+    pub comfort: Setpoint,
+    pub reduced: Setpoint,
+    pub frost: Setpoint,
+    pub mode: String,
+    pub boost: bool,
+    pub r#override: String,
+    /// local time the override ends, `YYYY-MM-DDTHH:MM`, reverted by `run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_until: Option<String>,
+    /// named lists of comfort ranges, referenced by the schedule
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Vec<String>>,
+    /// comfort ranges by days, e.g. `mon-fri = "workday"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedule: BTreeMap<String, DaySchedule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tuesday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wednesday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thursday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub friday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub saturday: Vec<Prog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sunday: Vec<Prog>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Prog {
+    pub timeframe: String,
+    pub mode: String,
+}
+
+/// Read the config file, overridden by the environment and `key=value`
+/// command line values.
+pub fn read(path: &str, sets: &[String]) -> Result<Config, ConfigError> {
+    let content = read_to_string(path)?;
+    let table = layers::merge(&content, std::env::vars(), sets)?;
+    let mut config: Config = table.try_into()?;
+    let version = config.version.unwrap_or(1);
+    migrate::check(version)?;
+    if version < migrate::VERSION {
+        eprintln!(
+            "config version {} is outdated, upgrade it with `config migrate`",
+            version
+        );
+    }
+    config.state_path = match &config.state {
+        Some(state) => state.clone(),
+        None => Path::new(path)
+            .with_extension("state.toml")
+            .to_string_lossy()
+            .into(),
+    };
+    config.path = path.into();
+    config.sets = sets.to_vec();
+    config.base_area1 = config.area1.clone();
+    config.move_legacy_devices();
+    config.load_state()?;
+    // request ids are saved to the state file as they are used
+    if !Path::new(&config.state_path).exists() {
+        config.update(|_| Ok::<_, ConfigError>(()))?;
+    }
+    Ok(config)
+}
+
+fn satellite_role(zone: u8) -> Result<Role, ConfigError> {
+    match zone {
+        1 => Ok(Role::SatelliteZ1),
+        2 => Ok(Role::SatelliteZ2),
+        3 => Ok(Role::SatelliteZ3),
+        _ => Err(ConfigError::new(&format!("invalid zone: {}", zone))),
+    }
+}
+
+/// JSON Schema of the config file, for editors.
+pub fn schema() -> Result<String, ConfigError> {
+    serde_json::to_string_pretty(&schema_for!(Config)).map_err(|e| ConfigError::new(&e.to_string()))
+}
+
+impl Config {
+    /// Last change of the config file.
+    pub fn modified(&self) -> Result<SystemTime, ConfigError> {
+        Ok(metadata(&self.path)?.modified()?)
+    }
+
+    /// Read the config file again, with the same overrides, and validate it.
+    pub fn reload(&self) -> Result<Config, ConfigError> {
+        let mut config = read(&self.path, &self.sets)?;
+        config.selected = self.selected.clone();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check values only used once sent to the boiler.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(area) = &self.area1 {
+            area.validate()
+                .map_err(|e| ConfigError::new(&format!("area1: {}", e)))?;
+        }
+        for satellite in self.satellites.iter().flatten() {
+            if !(1..=3).contains(&satellite.zone) {
+                Err(ConfigError::new(&format!(
+                    "invalid satellite zone: {}",
+                    satellite.zone
+                )))?;
+            }
+            self.device_name(satellite_role(satellite.zone)?)
+                .map_err(|e| {
+                    ConfigError::new(&format!("satellite zone {}: {}", satellite.zone, e))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Lock the state file, reload it, then save the changes made by
+    /// `change`. Other commands and the daemon wait meanwhile, so keep it
+    /// short.
+    pub fn update<T, E: From<ConfigError>>(
+        &mut self,
+        change: impl FnOnce(&mut Config) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let _lock = state::lock(&self.state_path)?;
+        self.load_state()?;
+        let result = change(self)?;
+        self.write()?;
+        Ok(result)
+    }
+
+    /// Devices and area1 changes from the state file, the configured ones
+    /// until it is first written. Reading needs no lock, the file is
+    /// replaced atomically.
+    pub fn load_state(&mut self) -> Result<(), ConfigError> {
+        let Some(state) = State::read(&self.state_path)? else {
+            return Ok(());
+        };
+        self.devices = state.devices;
+        self.last_outdoor = state.outdoor;
+        self.area1 = self.base_area1.clone();
+        if let Some(area) = &mut self.area1 {
+            state.area1.apply(area);
+        }
+        Ok(())
+    }
+
+    /// Write the state file, the config file is left untouched.
+    fn write(&self) -> Result<(), ConfigError> {
+        let area1 = match (&self.base_area1, &self.area1) {
+            (Some(base), Some(area)) => AreaState::diff(base, area),
+            _ => AreaState::default(),
+        };
+        State {
+            devices: self.devices.clone(),
+            area1,
+            outdoor: self.last_outdoor.clone(),
+        }
+        .write(&self.state_path)
+    }
+
+    pub fn frisquet(&mut self) -> Result<&mut Frisquet, ConfigError> {
+        self.device(Role::Connect)
+    }
+    pub fn sonde(&mut self) -> Result<&mut Frisquet, ConfigError> {
+        self.device(Role::Sonde)
+    }
+    pub fn has_sonde(&self) -> bool {
+        self.devices.values().any(|d| d.role == Role::Sonde)
+    }
+
+    pub fn satellite(&mut self, zone: u8) -> Result<&mut Frisquet, ConfigError> {
+        self.device(satellite_role(zone)?)
+    }
+
+    /// Association of the device with the given role: the selected device if
+    /// it has this role, otherwise the only device with this role.
+    pub fn device(&mut self, role: Role) -> Result<&mut Frisquet, ConfigError> {
+        let name = self.device_name(role)?;
+        let state_path = self.state_path.clone();
+        match self.devices.get_mut(&name) {
+            Some(device) => {
+                device.association.state = Some((state_path, name));
+                Ok(&mut device.association)
+            }
+            None => Err(ConfigError::new(&format!("unknown device: {}", name))),
+        }
+    }
+
+    fn device_name(&self, role: Role) -> Result<String, ConfigError> {
+        let names: Vec<&String> = self
+            .devices
+            .iter()
+            .filter(|(_, d)| d.role == role)
+            .map(|(name, _)| name)
+            .collect();
+
+        match (names.as_slice(), &self.selected) {
+            ([], _) => Err(ConfigError::new(&format!(
+                "missing required config: {} device",
+                role
+            ))),
+            (names, Some(selected)) if names.contains(&selected) => Ok(selected.clone()),
+            ([name], _) => Ok(name.to_string()),
+            (_, _) => Err(ConfigError::new(&format!(
+                "several {} devices, select one with --device",
+                role
+            ))),
+        }
+    }
+
+    fn move_legacy_devices(&mut self) {
+        let legacy = [
+            (Role::Connect, self.frisquet.take()),
+            (Role::Sonde, self.sonde.take()),
+            (Role::SatelliteZ1, self.satellite_z1.take()),
+            (Role::SatelliteZ2, self.satellite_z2.take()),
+            (Role::SatelliteZ3, self.satellite_z3.take()),
+        ];
+        for (role, association) in legacy {
+            if let Some(association) = association {
+                self.devices
+                    .entry(role.to_string())
+                    .or_insert(Device { role, association });
+            }
+        }
+    }
+
+    pub fn home_assistant(&mut self) -> Result<&mut HAConfig, ConfigError> {
+        match &mut self.home_assistant {
+            Some(home_assistant) => Ok(home_assistant),
+            None => Err(ConfigError::new("missing required config: sonde")),
+        }
+    }
+
+    pub fn area1(&mut self) -> Result<&mut Area, ConfigError> {
+        match &mut self.area1 {
+            Some(area) => Ok(area),
+            None => Err(ConfigError::new("missing required config: area1")),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            Role::Connect => "connect",
+            Role::Sonde => "sonde",
+            Role::SatelliteZ1 => "satellite-z1",
+            Role::SatelliteZ2 => "satellite-z2",
+            Role::SatelliteZ3 => "satellite-z3",
+        };
+        write!(f, "{}", role)
+    }
+}
+
+impl Frisquet {
+    pub fn network_id(&self) -> Result<Vec<u8>, ConfigError> {
+        match self.network_id {
+            Some(id) => Ok(Vec::from(id)),
+            None => Err(ConfigError::new(
+                "missing required config: frisquet.network_id",
+            )),
+        }
+    }
+    pub fn association_id(&self) -> Result<u8, ConfigError> {
+        match self.association_id {
+            Some(id) => Ok(id),
+            None => Err(ConfigError::new(
+                "missing required config: frisquet.association_id",
+            )),
+        }
+    }
+    /// Next request id, saved right away in the state file so that commands
+    /// running alongside the daemon never reuse one.
+    pub fn next_req_id(&mut self) -> Result<u8, ConfigError> {
+        let Some((path, name)) = self.state.clone() else {
+            return self.increment_req_id();
+        };
+        let _lock = state::lock(&path)?;
+        let mut state = State::read(&path)?.unwrap_or_default();
+        let Some(device) = state.devices.get_mut(&name) else {
+            return self.increment_req_id();
+        };
+        if device.association.request_id.is_some() {
+            self.request_id = device.association.request_id;
+        }
+        let req_id = self.increment_req_id()?;
+        device.association.request_id = Some(req_id);
+        state.write(&path)?;
+        Ok(req_id)
+    }
+
+    fn increment_req_id(&mut self) -> Result<u8, ConfigError> {
+        match self.request_id {
+            Some(id) => {
+                let (req_id, _) = id.overflowing_add(4);
+                self.request_id = Some(req_id);
+                Ok(req_id)
+            }
+            None => Err(ConfigError::new(
+                "missing required config: frisquet.request_id",
+            )),
+        }
+    }
+}
+
+impl Area {
+    pub fn mode(&self) -> Result<u8, ConfigError> {
+        match self.mode.as_str() {
+            "auto" => Ok(0x05),
+            "comfort" => Ok(0x06),
+            "reduced" => Ok(0x07),
+            "frost" => Ok(0x08),
+            x => Err(ConfigError::new(format!("invalid mode: {}", x).as_str())),
+        }
+    }
+    pub fn mode_is_auto(&self) -> Result<bool, ConfigError> {
+        Ok(self.mode()? == 0x05)
+    }
+    pub fn mode_is_comfort(&self) -> Result<bool, ConfigError> {
+        Ok(self.mode()? == 0x06)
+    }
+    pub fn comfort_override(&self) -> Result<Option<bool>, ConfigError> {
+        match self.r#override.as_str() {
+            "comfort" => Ok(Some(true)),
+            "reduced" => Ok(Some(false)),
+            "none" => Ok(None),
+            x => Err(ConfigError::new(format!("invalid mode: {}", x).as_str())),
+        }
+    }
+    pub fn override_until(&self) -> Result<Option<NaiveDateTime>, ConfigError> {
+        match &self.override_until {
+            Some(until) => NaiveDateTime::parse_from_str(until, OVERRIDE_UNTIL_FORMAT)
+                .map(Some)
+                .map_err(|_| ConfigError::new(&format!("invalid override_until: {}", until))),
+            None => Ok(None),
+        }
+    }
+    /// Whether the override ends at or before `now`.
+    pub fn override_expired(&self, now: NaiveDateTime) -> Result<bool, ConfigError> {
+        Ok(self.override_until()?.is_some_and(|until| until <= now))
+    }
+    pub fn monday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Mon)
+    }
+    pub fn tuesday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Tue)
+    }
+    pub fn wednesday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Wed)
+    }
+    pub fn thursday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Thu)
+    }
+    pub fn friday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Fri)
+    }
+    pub fn saturday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Sat)
+    }
+    pub fn sunday(&self) -> Result<[u8; 6], ConfigError> {
+        self.day(Weekday::Sun)
+    }
+    /// Whether the zone is in comfort at a time, as sent to the boiler: the
+    /// schedule slot in auto mode, the selected mode otherwise.
+    pub fn comfort_at(&self, day: Weekday, hour: u8, minute: u8) -> Result<bool, ConfigError> {
+        if !self.mode_is_auto()? {
+            return self.mode_is_comfort();
+        }
+        if hour >= 24 || minute >= 60 {
+            return Err(ConfigError::new(&format!(
+                "invalid time {:02}:{:02}",
+                hour, minute
+            )));
+        }
+        let slot = hour * 2 + minute / 30;
+        Ok(self.day(day)?[(slot / 8) as usize] & (1 << (slot % 8)) != 0)
+    }
+    /// Check temperatures, modes and schedule.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.mode()?;
+        self.comfort_override()?;
+        self.override_until()?;
+        if !self.schedule.is_empty() {
+            schedule::resolve(&self.schedule, &self.profiles)?;
+        }
+        for day in schedule::WEEK {
+            self.day(day)
+                .map_err(|e| ConfigError::new(&format!("{}: {}", schedule::day_name(day), e)))?;
+        }
+        Ok(())
+    }
+    /// Comfort slots of a day, from `schedule` or the per day tables.
+    pub fn day(&self, day: Weekday) -> Result<[u8; 6], ConfigError> {
+        let legacy = match day {
+            Weekday::Mon => &self.monday,
+            Weekday::Tue => &self.tuesday,
+            Weekday::Wed => &self.wednesday,
+            Weekday::Thu => &self.thursday,
+            Weekday::Fri => &self.friday,
+            Weekday::Sat => &self.saturday,
+            Weekday::Sun => &self.sunday,
+        };
+        if self.schedule.is_empty() {
+            return to_mode_prog(legacy);
+        }
+        if !legacy.is_empty() {
+            return Err(ConfigError::new(&format!(
+                "{} is defined both in area1.schedule and area1.{}",
+                schedule::day_name(day),
+                schedule::day_name(day)
+            )));
+        }
+        Ok(schedule::resolve(&self.schedule, &self.profiles)?[&day])
+    }
+}
+
+pub fn to_mode_prog(prog: &Vec<Prog>) -> Result<[u8; 6], ConfigError> {
+    let mut comfort = vec![];
+    for s in prog {
+        match s.mode.as_str() {
+            "comfort" => comfort.push(s.timeframe.as_str()),
+            "reduced" => {
+                continue; // reduced is default
+            }
+            x => {
+                return Err(ConfigError::new(format!("invalid mode: {}", x).as_str()));
+            }
+        }
+    }
+    schedule::to_bitmap(&comfort)
+}
+
+fn slice_as_hex<T, S>(option: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: Serializer,
+{
+    match option {
+        Some(src) => {
+            let data = hex::encode(src.as_ref());
+            serializer.serialize_str(&data)
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
+fn slice_from_hex<'de, const N: usize, D>(deserializer: D) -> Result<Option<[u8; N]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let option: Option<String> = Deserialize::deserialize(deserializer)?;
+    match option {
+        Some(src) => {
+            let data = hex::decode(src).map_err(|e| Error::custom(e.to_string()))?;
+
+            let len = data.len();
+            let res: [u8; N] = data.try_into().map_err(|_| {
+                let expected = format!("[u8; {}]", N);
+                Error::invalid_length(len, &expected.as_str())
+            })?;
+
+            Ok(Some(res))
+        }
+        None => Ok(None),
+    }
+}
+
+fn u8_as_hex<S>(option: &Option<u8>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match option {
+        Some(src) => {
+            let data = hex::encode(vec![*src]);
+            serializer.serialize_str(&data)
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
+fn u8_from_hex<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let option: Option<String> = Deserialize::deserialize(deserializer)?;
+    match option {
+        Some(src) => {
+            let data = hex::decode(src).map_err(|e| Error::custom(e.to_string()))?;
+
+            if data.len() != 1 {
+                let expected = format!("u8");
+                return Err(Error::invalid_length(data.len(), &expected.as_str()));
+            }
+
+            Ok(Some(data[0]))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Indoor source, or a Home Assistant entity as written before any other
+/// source was supported.
+fn indoor_source<'de, D>(deserializer: D) -> Result<TemperatureSource, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let value = toml::Value::deserialize(deserializer)?;
+    if value.get("entity_id").is_some() {
+        return Ok(TemperatureSource {
+            homeassistant: Some(value.try_into().map_err(Error::custom)?),
+            ..Default::default()
+        });
+    }
+    value.try_into().map_err(Error::custom)
+}
+
+/// Any scalar as a string, e.g. a numeric password from the environment.
+fn string_from_scalar<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    match toml::Value::deserialize(deserializer)? {
+        toml::Value::String(s) => Ok(s),
+        value @ (toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_)) => {
+            Ok(value.to_string())
+        }
+        value => Err(Error::custom(format!(
+            "invalid type: {}, expected a string",
+            value.type_str()
+        ))),
+    }
+}
+
+fn option_from_scalar<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    string_from_scalar(deserializer).map(Some)
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ConfigError {
+    msg: String,
+}
+
+impl ConfigError {
+    pub fn new(msg: &str) -> ConfigError {
+        ConfigError { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        std::fmt::Display::fmt(&self.msg, f)
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> ConfigError {
+        ConfigError {
+            msg: err.to_string(),
+        }
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> ConfigError {
+        ConfigError {
+            msg: format!("invalid toml: {}", err.to_string()),
+        }
+    }
+}
+
+impl From<toml_edit::TomlError> for ConfigError {
+    fn from(err: toml_edit::TomlError) -> ConfigError {
+        ConfigError {
+            msg: format!("invalid toml: {}", err),
+        }
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(err: toml::ser::Error) -> ConfigError {
+        ConfigError {
+            msg: format!("fail to serialize toml: {}", err.to_string()),
+        }
+    }
+}
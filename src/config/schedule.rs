@@ -0,0 +1,349 @@
+use chrono::Weekday;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::ConfigError;
+
+/// Half hour slots in a day.
+pub const SLOTS: u8 = 48;
+
+pub const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Comfort ranges of the days of a `[area1.schedule]` entry: a profile name
+/// or ranges, e.g. `["06:30-08:00", "17:30-23:00"]`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DaySchedule {
+    Profile(String),
+    Ranges(Vec<String>),
+}
+
+/// Comfort slots of each day from a schedule, keyed by days such as `mon`,
+/// `mon-fri`, `sat,sun`, `weekend` or `all`. Every day is scheduled once.
+pub fn resolve(
+    schedule: &BTreeMap<String, DaySchedule>,
+    profiles: &BTreeMap<String, Vec<String>>,
+) -> Result<HashMap<Weekday, [u8; 6]>, ConfigError> {
+    let mut days: HashMap<Weekday, (&str, [u8; 6])> = HashMap::new();
+    for (spec, entry) in schedule {
+        let ranges = match entry {
+            DaySchedule::Profile(name) => profiles.get(name).ok_or_else(|| {
+                ConfigError::new(&format!("schedule {}: unknown profile {}", spec, name))
+            })?,
+            DaySchedule::Ranges(ranges) => ranges,
+        };
+        let bitmap = to_bitmap(ranges)
+            .map_err(|e| ConfigError::new(&format!("schedule {}: {}", spec, e)))?;
+
+        for day in parse_days(spec)? {
+            if let Some((other, _)) = days.get(&day) {
+                return Err(ConfigError::new(&format!(
+                    "{} is scheduled twice: {} and {}",
+                    day_name(day),
+                    other,
+                    spec
+                )));
+            }
+            days.insert(day, (spec, bitmap));
+        }
+    }
+
+    let missing: Vec<&str> = WEEK
+        .iter()
+        .filter(|day| !days.contains_key(day))
+        .map(|day| day_name(*day))
+        .collect();
+    if !missing.is_empty() {
+        return Err(ConfigError::new(&format!(
+            "no schedule for {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(days
+        .into_iter()
+        .map(|(day, (_, bitmap))| (day, bitmap))
+        .collect())
+}
+
+/// Comfort slots of ranges, overlapping ranges are rejected.
+pub fn to_bitmap<S: AsRef<str>>(ranges: &[S]) -> Result<[u8; 6], ConfigError> {
+    let mut parsed: Vec<(u8, u8, &str)> = vec![];
+    for range in ranges {
+        let range = range.as_ref();
+        let (start, end) = parse_range(range)?;
+        if let Some((_, _, other)) = parsed.iter().find(|(s, e, _)| start < *e && *s < end) {
+            return Err(ConfigError::new(&format!(
+                "overlapping ranges {} and {}",
+                other, range
+            )));
+        }
+        parsed.push((start, end, range));
+    }
+
+    let mut res = [0u8; 6];
+    for (start, end, _) in parsed {
+        for n in start..end {
+            res[(n / 8) as usize] |= 1 << (n % 8);
+        }
+    }
+    Ok(res)
+}
+
+/// Minimal comfort ranges of a day bitmap, contiguous slots merged.
+pub fn from_bitmap(bitmap: &[u8; 6]) -> Vec<(u8, u8)> {
+    let mut ranges: Vec<(u8, u8)> = vec![];
+    for n in (0..SLOTS).filter(|n| bitmap[(n / 8) as usize] & (1 << (n % 8)) != 0) {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == n => *end = n + 1,
+            _ => ranges.push((n, n + 1)),
+        }
+    }
+    ranges
+}
+
+/// `HH:MM-HH:MM` ranges of a day bitmap.
+pub fn to_ranges(bitmap: &[u8; 6]) -> Vec<String> {
+    from_bitmap(bitmap).into_iter().map(format_range).collect()
+}
+
+/// `HH:MM-HH:MM`, the end of day is `24:00`.
+pub fn format_range((start, end): (u8, u8)) -> String {
+    format!(
+        "{:02}:{:02}-{:02}:{:02}",
+        start / 2,
+        start % 2 * 30,
+        end / 2,
+        end % 2 * 30
+    )
+}
+
+/// Slots of a `HH:MM-HH:MM` (or `HHhMM-HHhMM`) range, end excluded. An end
+/// at `00:00` or `24:00` is midnight, end of day.
+pub fn parse_range(range: &str) -> Result<(u8, u8), ConfigError> {
+    let invalid = || {
+        ConfigError::new(&format!(
+            "invalid range {}, expected HH:MM-HH:MM with 30 min increments",
+            range
+        ))
+    };
+
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let start = parse_time(start.trim()).ok_or_else(invalid)?;
+    let mut end = parse_time(end.trim()).ok_or_else(invalid)?;
+    if start > 0 && end == 0 {
+        end = SLOTS;
+    }
+    if start == SLOTS || end < start {
+        return Err(invalid());
+    }
+    if end == start {
+        return Err(ConfigError::new(&format!(
+            "empty range {}, use 00:00-24:00 for a full day",
+            range
+        )));
+    }
+    Ok((start, end))
+}
+
+fn parse_time(time: &str) -> Option<u8> {
+    let (hours, minutes) = time.split_once(':').or_else(|| time.split_once('h'))?;
+    let hours: u8 = hours.parse().ok()?;
+    let minutes: u8 = minutes.parse().ok()?;
+    match (hours, minutes) {
+        (24, 0) => Some(SLOTS),
+        (0..=23, 0 | 30) => Some(hours * 2 + minutes / 30),
+        _ => None,
+    }
+}
+
+/// Days of a spec: comma separated days (`mon`, `monday`), day ranges
+/// (`mon-fri`, `fri-mon`), `weekdays`, `weekend` or `all`.
+pub fn parse_days(spec: &str) -> Result<Vec<Weekday>, ConfigError> {
+    let mut days = vec![];
+    for item in spec.split(',').map(str::trim) {
+        match item {
+            "all" => days.extend(WEEK),
+            "weekdays" => days.extend(&WEEK[0..5]),
+            "weekend" => days.extend(&WEEK[5..7]),
+            item => match item.split_once('-') {
+                Some((first, last)) => {
+                    let first = parse_day(first)?.num_days_from_monday() as usize;
+                    let last = parse_day(last)?.num_days_from_monday() as usize;
+                    let count = (last + 7 - first) % 7 + 1;
+                    days.extend((0..count).map(|n| WEEK[(first + n) % 7]));
+                }
+                None => days.push(parse_day(item)?),
+            },
+        }
+    }
+
+    let mut seen = vec![];
+    for day in days {
+        if seen.contains(&day) {
+            return Err(ConfigError::new(&format!(
+                "{}: {} listed twice",
+                spec,
+                day_name(day)
+            )));
+        }
+        seen.push(day);
+    }
+    Ok(seen)
+}
+
+fn parse_day(day: &str) -> Result<Weekday, ConfigError> {
+    WEEK.iter()
+        .copied()
+        .find(|d| day == &day_name(*d)[0..3] || day == day_name(*d))
+        .ok_or_else(|| ConfigError::new(&format!("invalid day: {}", day)))
+}
+
+pub fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("06:30-08:00"), Ok((13, 16)));
+        assert_eq!(parse_range("06h30-23h30"), Ok((13, 47)));
+        assert_eq!(parse_range("17:00-00:00"), Ok((34, 48)));
+        assert_eq!(parse_range("00:00-24:00"), Ok((0, 48)));
+        assert!(parse_range("06:15-08:00").is_err());
+        assert!(parse_range("08:00-06:00").is_err());
+        assert!(parse_range("08:00-08:00").is_err());
+        assert!(parse_range("00:00-00:00").is_err());
+        assert!(parse_range("08:00").is_err());
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("mon-fri").unwrap().len(), 5);
+        assert_eq!(
+            parse_days("fri-mon").unwrap(),
+            vec![Weekday::Fri, Weekday::Sat, Weekday::Sun, Weekday::Mon]
+        );
+        assert_eq!(
+            parse_days("weekend").unwrap(),
+            vec![Weekday::Sat, Weekday::Sun]
+        );
+        assert_eq!(
+            parse_days("monday, wed").unwrap(),
+            vec![Weekday::Mon, Weekday::Wed]
+        );
+        assert_eq!(parse_days("all").unwrap().len(), 7);
+        assert!(parse_days("mon,mon-tue").is_err());
+        assert!(parse_days("mo").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let profiles = BTreeMap::from([(
+            "workday".to_string(),
+            vec!["06:30-08:00".to_string(), "17:30-23:00".to_string()],
+        )]);
+        let mut schedule = BTreeMap::from([
+            (
+                "mon-fri".to_string(),
+                DaySchedule::Profile("workday".into()),
+            ),
+            (
+                "weekend".to_string(),
+                DaySchedule::Ranges(vec!["08:00-00:00".into()]),
+            ),
+        ]);
+
+        let days = resolve(&schedule, &profiles).unwrap();
+        assert_eq!(
+            days[&Weekday::Tue],
+            to_bitmap(&profiles["workday"]).unwrap()
+        );
+        assert_eq!(days[&Weekday::Sun], [0, 0, 0xff, 0xff, 0xff, 0xff]);
+
+        schedule.insert("sun".into(), DaySchedule::Profile("workday".into()));
+        let err = resolve(&schedule, &profiles).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sunday is scheduled twice: sun and weekend"
+        );
+
+        schedule.remove("weekend");
+        let err = resolve(&schedule, &profiles).unwrap_err();
+        assert_eq!(err.to_string(), "no schedule for saturday");
+
+        schedule.insert("sat".into(), DaySchedule::Profile("home".into()));
+        assert!(resolve(&schedule, &profiles).is_err());
+    }
+
+    #[test]
+    fn test_from_bitmap() {
+        let bitmap = to_bitmap(&["00:00-01:00", "06:00-07:00", "07:00-00:00"]).unwrap();
+        assert_eq!(from_bitmap(&bitmap), vec![(0, 2), (12, 48)]);
+        assert_eq!(to_ranges(&bitmap), vec!["00:00-01:00", "06:00-24:00"]);
+        assert!(to_ranges(&[0; 6]).is_empty());
+        assert_eq!(to_ranges(&[0xff; 6]), vec!["00:00-24:00"]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_bitmap_round_trip(bitmap in any::<[u8; 6]>()) {
+            let ranges = to_ranges(&bitmap);
+            prop_assert_eq!(to_bitmap(&ranges).unwrap(), bitmap);
+
+            // minimal: ranges are neither empty nor adjacent
+            let slots = from_bitmap(&bitmap);
+            prop_assert!(slots.iter().all(|(start, end)| start < end));
+            prop_assert!(slots.windows(2).all(|w| w[0].1 < w[1].0));
+        }
+
+        #[test]
+        fn test_ranges_round_trip(mut bounds in prop::collection::btree_set(0..=SLOTS, 0..12)) {
+            // disjoint ranges from sorted distinct bounds
+            if bounds.len() % 2 == 1 {
+                bounds.pop_last();
+            }
+            let bounds: Vec<u8> = bounds.into_iter().collect();
+            let ranges: Vec<String> = bounds
+                .chunks(2)
+                .map(|c| format_range((c[0], c[1])))
+                .collect();
+
+            let bitmap = to_bitmap(&ranges).unwrap();
+            let slots = bounds.chunks(2).map(|c| (c[1] - c[0]) as u32).sum::<u32>();
+            prop_assert_eq!(bitmap.iter().map(|b| b.count_ones()).sum::<u32>(), slots);
+            prop_assert_eq!(to_bitmap(&to_ranges(&bitmap)).unwrap(), bitmap);
+        }
+    }
+
+    #[test]
+    fn test_overlap() {
+        let err = to_bitmap(&["06:30-08:00", "07:30-09:00"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "overlapping ranges 06:30-08:00 and 07:30-09:00"
+        );
+        assert!(to_bitmap(&["06:30-08:00", "08:00-09:00"]).is_ok());
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, rename, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use super::{Area, ConfigError, Device};
+
+/// Values written by commands: associations, request ids, flags and area
+/// changes. Kept apart from the user config, which is never written.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    #[serde(default)]
+    pub devices: BTreeMap<String, Device>,
+    /// area1 values differing from the config
+    #[serde(default, skip_serializing_if = "AreaState::is_empty")]
+    pub area1: AreaState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outdoor: Option<OutdoorState>,
+}
+
+/// Last good outdoor temperature, sent again after a restart while the
+/// source is down.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutdoorState {
+    pub value: f32,
+    /// unix time
+    pub at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct AreaState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boost: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#override: Option<String>,
+    /// empty when the configured end was cleared
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_until: Option<String>,
+}
+
+impl AreaState {
+    pub fn is_empty(&self) -> bool {
+        *self == AreaState::default()
+    }
+
+    /// Changes from `base` to `area`.
+    pub fn diff(base: &Area, area: &Area) -> AreaState {
+        fn changed<T: PartialEq + Clone>(base: &T, value: &T) -> Option<T> {
+            (base != value).then(|| value.clone())
+        }
+        AreaState {
+            mode: changed(&base.mode, &area.mode),
+            boost: changed(&base.boost, &area.boost),
+            r#override: changed(&base.r#override, &area.r#override),
+            override_until: changed(&base.override_until, &area.override_until)
+                .map(Option::unwrap_or_default),
+        }
+    }
+
+    pub fn apply(&self, area: &mut Area) {
+        if let Some(mode) = &self.mode {
+            area.mode = mode.clone();
+        }
+        if let Some(boost) = self.boost {
+            area.boost = boost;
+        }
+        if let Some(r#override) = &self.r#override {
+            area.r#override = r#override.clone();
+        }
+        if let Some(until) = &self.override_until {
+            area.override_until = (!until.is_empty()).then(|| until.clone());
+        }
+    }
+}
+
+impl State {
+    /// `None` when the state file does not exist yet.
+    pub fn read(path: &str) -> Result<Option<State>, ConfigError> {
+        match read_to_string(path) {
+            Ok(content) => Ok(Some(toml::from_str(&content)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ConfigError::new(&format!("{}: {}", path, e))),
+        }
+    }
+
+    /// Replace the state file, a crash leaves either the old or the new one.
+    pub fn write(&self, path: &str) -> Result<(), ConfigError> {
+        let content = toml::to_string_pretty(self)?;
+        let tmp = format!("{}.tmp", path);
+        let mut file = File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        rename(&tmp, path)?;
+
+        // persist the rename
+        let dir = match Path::new(path).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Exclusive lock of a state file, released on drop.
+pub struct StateLock {
+    _file: File,
+}
+
+/// Wait for the lock of the state file at `path`.
+pub fn lock(path: &str) -> Result<StateLock, ConfigError> {
+    let lock_path = format!("{}.lock", path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            eprintln!("waiting for {}", lock_path);
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => Err(e)?,
+    }
+    Ok(StateLock { _file: file })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Frisquet, Role};
+    use std::fs::remove_file;
+
+    fn area() -> Area {
+        toml::from_str(
+            r#"
+            comfort = 21.0
+            reduced = 19.0
+            frost = 8.0
+            mode = "auto"
+            boost = false
+            override = "none"
+            override_until = "2024-10-12T22:30"
+            [schedule]
+            all = []
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_area_diff() {
+        let base = area();
+        assert!(AreaState::diff(&base, &base).is_empty());
+
+        let mut area = area();
+        area.boost = true;
+        area.override_until = None;
+        let diff = AreaState::diff(&base, &area);
+        assert_eq!(diff.boost, Some(true));
+        assert_eq!(diff.mode, None);
+
+        let mut applied = base.clone();
+        diff.apply(&mut applied);
+        assert!(applied.boost);
+        assert_eq!(applied.override_until, None);
+    }
+
+    #[test]
+    fn test_write_read() {
+        let path = std::env::temp_dir()
+            .join(format!("frisquet-state-{}.toml", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        assert!(State::read(&path).unwrap().is_none());
+
+        let mut state = State::default();
+        state.devices.insert(
+            "connect".into(),
+            Device {
+                role: Role::Connect,
+                association: Frisquet {
+                    send_init: None,
+                    network_id: Some([0x12, 0x34, 0x56, 0x78]),
+                    association_id: Some(0xd4),
+                    request_id: Some(0x10),
+                    ..Default::default()
+                },
+            },
+        );
+        {
+            let _lock = lock(&path).unwrap();
+            state.write(&path).unwrap();
+        }
+
+        let read = State::read(&path).unwrap().unwrap();
+        assert_eq!(read.devices["connect"].association.request_id, Some(0x10));
+        assert!(read.area1.is_empty());
+        remove_file(&path).unwrap();
+        remove_file(format!("{}.lock", path)).unwrap();
+    }
+
+    #[test]
+    fn test_shared_req_id() {
+        let path = std::env::temp_dir()
+            .join(format!("frisquet-req-id-{}.toml", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let frisquet = Frisquet {
+            request_id: Some(0x10),
+            ..Default::default()
+        };
+        let mut state = State::default();
+        state.devices.insert(
+            "connect".into(),
+            Device {
+                role: Role::Connect,
+                association: frisquet.clone(),
+            },
+        );
+        state.write(&path).unwrap();
+
+        // a command and the daemon, each with its own copy
+        let mut command = frisquet.clone();
+        command.state = Some((path.clone(), "connect".into()));
+        let mut daemon = command.clone();
+        assert_eq!(command.next_req_id(), Ok(0x14));
+        assert_eq!(daemon.next_req_id(), Ok(0x18));
+        assert_eq!(command.next_req_id(), Ok(0x1c));
+        let read = State::read(&path).unwrap().unwrap();
+        assert_eq!(read.devices["connect"].association.request_id, Some(0x1c));
+        remove_file(&path).unwrap();
+        remove_file(format!("{}.lock", path)).unwrap();
+    }
+
+    #[test]
+    fn test_created_on_read() {
+        let dir = std::env::temp_dir().join(format!("frisquet-read-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml").to_string_lossy().to_string();
+        std::fs::write(
+            &path,
+            r#"
+            version = 2
+            [devices.connect]
+            role = "connect"
+            network_id = "12345678"
+            association_id = "d4"
+            request_id = "10"
+            "#,
+        )
+        .unwrap();
+
+        let mut config = crate::config::read(&path, &[]).unwrap();
+        assert!(Path::new(&config.state_path).exists());
+        // device() doesn't write, so it can run while the state is locked
+        let association_id = config
+            .update(|config| config.device(Role::Connect).map(|d| d.association_id))
+            .unwrap();
+        assert_eq!(association_id, Some(0xd4));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use colored::Colorize;
+use deku::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::connect::decoder::{Correlator, Direction};
+use crate::connect::{date, sensors, Assert, Metadata};
+
+const MIN_CORRELATION: f32 = 0.9;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct GroupKey {
+    pub cmd: String,
+    pub response: bool,
+    pub from_addr: u8,
+    pub to_addr: u8,
+    pub len: usize,
+}
+
+type Known = BTreeMap<&'static str, f32>;
+
+/// Group captured frames by command and track the known values (sensors,
+/// boiler clock) at the time each frame was seen.
+#[derive(Default)]
+pub struct Analyzer {
+    correlator: Correlator,
+    known: Known,
+    groups: BTreeMap<GroupKey, Vec<(Vec<u8>, Known)>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OffsetReport {
+    pub offset: usize,
+    pub min: u8,
+    pub max: u8,
+    pub distinct: usize,
+    pub correlation: Option<(&'static str, bool, f32)>, // name, 16 bits word, r
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GroupReport {
+    pub key: GroupKey,
+    pub frames: usize,
+    pub offsets: Vec<OffsetReport>,
+}
+
+/// Payload of a frame, none when it is truncated or corrupt.
+fn decode<'a, T>(frame: &'a [u8]) -> Option<T>
+where
+    T: DekuContainerRead<'a> + Assert,
+{
+    let (_, data) = T::from_bytes((frame.get(7..)?, 0)).ok()?;
+    data.assert().then_some(data)
+}
+
+impl Analyzer {
+    pub fn new() -> Analyzer {
+        Analyzer::default()
+    }
+
+    pub fn push(&mut self, frame: &[u8]) -> Result<(), DekuError> {
+        let (_, meta) = Metadata::from_bytes((frame, 0))?;
+        let payload = frame[7..].to_vec();
+        let response = Direction::of(&meta) == Direction::Response;
+        let cmd = self
+            .correlator
+            .command_id(&meta, &payload)
+            .unwrap_or("?".into());
+
+        if response {
+            self.update_known(&cmd, frame);
+        }
+
+        let key = GroupKey {
+            cmd,
+            response,
+            from_addr: meta.from_addr,
+            to_addr: meta.to_addr,
+            len: payload.len(),
+        };
+        self.groups
+            .entry(key)
+            .or_default()
+            .push((payload, self.known.clone()));
+        Ok(())
+    }
+
+    fn update_known(&mut self, cmd: &str, frame: &[u8]) {
+        match cmd {
+            "79e0001c" => {
+                if let Some(msg) = decode::<sensors::SensorsMsg>(frame) {
+                    for (name, value) in msg.temperatures() {
+                        self.known.insert(name, value.tenths() as f32);
+                    }
+                }
+            }
+            "a02b0004" => {
+                if let Some(msg) = decode::<date::DateMsg>(frame) {
+                    self.known.insert("Clock hour", msg.hour() as f32);
+                    self.known.insert("Clock minute", msg.minute() as f32);
+                    self.known.insert("Clock second", msg.second() as f32);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn report(&self) -> Vec<GroupReport> {
+        self.groups
+            .iter()
+            .map(|(key, samples)| GroupReport {
+                key: key.clone(),
+                frames: samples.len(),
+                offsets: (0..key.len).map(|n| offset_report(samples, n)).collect(),
+            })
+            .collect()
+    }
+}
+
+fn offset_report(samples: &[(Vec<u8>, Known)], offset: usize) -> OffsetReport {
+    let bytes: Vec<u8> = samples.iter().map(|(p, _)| p[offset]).collect();
+    let distinct = bytes.iter().collect::<BTreeSet<_>>().len();
+
+    let mut correlation: Option<(&'static str, bool, f32)> = None;
+    if distinct > 1 {
+        let names: BTreeSet<&'static str> = samples
+            .iter()
+            .flat_map(|(_, k)| k.keys().copied())
+            .collect();
+        for name in names {
+            let known: Vec<Option<f32>> =
+                samples.iter().map(|(_, k)| k.get(name).copied()).collect();
+
+            let byte: Vec<f32> = bytes.iter().map(|b| *b as f32).collect();
+            let word: Option<Vec<f32>> = samples
+                .iter()
+                .map(|(p, _)| {
+                    p.get(offset..offset + 2)
+                        .map(|w| i16::from_be_bytes([w[0], w[1]]) as f32)
+                })
+                .collect();
+
+            let mut candidates = vec![(false, pearson(&byte, &known))];
+            if let Some(word) = word {
+                candidates.push((true, pearson(&word, &known)));
+            }
+            for (is_word, r) in candidates {
+                let Some(r) = r else { continue };
+                let best = correlation.map_or(0.0, |(_, _, b)| b.abs());
+                if r.abs() >= MIN_CORRELATION && r.abs() > best {
+                    correlation = Some((name, is_word, r));
+                }
+            }
+        }
+    }
+
+    OffsetReport {
+        offset,
+        min: *bytes.iter().min().unwrap_or(&0),
+        max: *bytes.iter().max().unwrap_or(&0),
+        distinct,
+        correlation,
+    }
+}
+
+/// Pearson correlation over the samples where the known value is set.
+fn pearson(values: &[f32], known: &[Option<f32>]) -> Option<f32> {
+    let pairs: Vec<(f32, f32)> = values
+        .iter()
+        .zip(known)
+        .filter_map(|(v, k)| k.map(|k| (*v, k)))
+        .collect();
+    if pairs.len() < 3 {
+        return None;
+    }
+
+    let n = pairs.len() as f32;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f32>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f32>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x) * (x - mean_x);
+        var_y += (y - mean_y) * (y - mean_y);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+impl fmt::Display for GroupKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "0x{:02x} > 0x{:02x} {} {} ({} bytes)",
+            self.from_addr,
+            self.to_addr,
+            self.cmd,
+            if self.response { "response" } else { "request" },
+            self.len,
+        )
+    }
+}
+
+impl fmt::Display for GroupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            format!("== {}, {} frames", self.key, self.frames).cyan()
+        )?;
+        for o in &self.offsets {
+            if o.distinct == 1 {
+                write!(f, "\n  {:03}  const {:02x}", o.offset, o.min)?;
+                continue;
+            }
+            let line = format!(
+                "\n  {:03}  vary  {:02x}..{:02x} ({} values)",
+                o.offset, o.min, o.max, o.distinct
+            );
+            write!(f, "{}", line.yellow())?;
+            if let Some((name, word, r)) = o.correlation {
+                let width = if word { "word" } else { "byte" };
+                write!(
+                    f,
+                    "{}",
+                    format!("  ~ {} ({} r={:.2})", name, width, r).green()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_and_correlate() {
+        let mut analyzer = Analyzer::new();
+        for (n, temp) in [200i16, 215, 230, 260].iter().enumerate() {
+            let req_id = (n * 4) as u8;
+            let request = hex::decode(format!("0a807e12{:02x}010379e0001c", req_id)).unwrap();
+            analyzer.push(&request).unwrap();
+
+            let mut response = hex::decode(format!("3f7e8012{:02x}810338", req_id)).unwrap();
+            let mut data = vec![0u8; 56];
+            data[0..2].copy_from_slice(&temp.to_be_bytes()); // temp_ecs
+            data[20] = (temp / 10) as u8; // unknown byte following the temperature
+            data[30] = [3, 0, 2, 1][n]; // unrelated value
+            response.append(&mut data);
+            analyzer.push(&response).unwrap();
+        }
+
+        let report = analyzer.report();
+        assert_eq!(report.len(), 2);
+
+        let request = &report[0];
+        assert!(!request.key.response);
+        assert_eq!(request.frames, 4);
+        assert!(request.offsets.iter().all(|o| o.distinct == 1));
+
+        let response = &report[1];
+        assert!(response.key.response);
+        assert_eq!(response.key.cmd, "79e0001c");
+        assert_eq!(response.key.len, 57);
+        assert_eq!(response.offsets[0].distinct, 1); // len
+        assert_eq!(
+            response.offsets[21].correlation.map(|c| c.0),
+            Some("Temp ECS")
+        );
+        assert!(response.offsets[21].correlation.unwrap().2 > 0.99);
+        assert_eq!(response.offsets[22].distinct, 1);
+        assert_eq!(response.offsets[31].distinct, 4);
+        assert_eq!(response.offsets[31].correlation, None);
+    }
+
+    #[test]
+    fn test_corrupt_known_frame() {
+        let mut analyzer = Analyzer::new();
+        analyzer
+            .push(&hex::decode("0a8020ba400103a02b0004").unwrap())
+            .unwrap();
+        // wrong length byte, skipped instead of panicking
+        analyzer
+            .push(&hex::decode("0f2080ba4081030723040511311728").unwrap())
+            .unwrap();
+
+        assert!(analyzer.known.is_empty());
+        assert_eq!(analyzer.report()[1].key.cmd, "a02b0004");
+    }
+
+    #[test]
+    fn test_unmatched_response() {
+        let mut analyzer = Analyzer::new();
+        analyzer
+            .push(&hex::decode("0f2080ba408117082304051131172803").unwrap())
+            .unwrap();
+
+        let report = analyzer.report();
+        assert_eq!(report[0].key.cmd, "?");
+        assert!(report[0].key.response);
+    }
+}
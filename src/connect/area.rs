@@ -1,26 +1,35 @@
+use chrono::Weekday;
 use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::time::Duration;
 
 use crate::config;
-use crate::connect::date::connect_date;
-use crate::connect::{filter, format_day, from_bytes, send_cmd, ConnectError, DropMsg, Metadata};
+use crate::config::schedule::{to_ranges, DaySchedule};
+use crate::connect::date::{connect_date, try_connect_date, DateMsg};
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::serialize_hex;
+use crate::connect::temperature::Setpoint;
+use crate::connect::{format_day, send_cmd, wait_response, ConnectError, Metadata};
 use crate::rf::RFClient;
 
 use super::Assert;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
 pub struct AreaMsg {
-    cmd: [u8; 4],     // cmd
-    cmd2: [u8; 4],    // re cmd
-    len: u8,          // length
-    temp_comfort: u8, // start at 5°C - 0 is 50
-    temp_reduced: u8, // start at 5°C - 0 is 50
-    temp_frost: u8,   // start at 5°C - 0 is 50
-    mode: u8,         // 05 auto - 06 confort - 07 reduit - 08 hors gel
+    #[serde(serialize_with = "serialize_hex")]
+    cmd: [u8; 4], // cmd
+    #[serde(serialize_with = "serialize_hex")]
+    cmd2: [u8; 4], // re cmd
+    len: u8, // length
+    temp_comfort: Setpoint,
+    temp_reduced: Setpoint,
+    temp_frost: Setpoint,
+    mode: u8, // 05 auto - 06 confort - 07 reduit - 08 hors gel
     #[deku(bits = "1")]
     unknow_mode: bool,
     #[deku(bits = "1")]
@@ -34,12 +43,19 @@ pub struct AreaMsg {
     #[deku(bits = "1")]
     confort: bool,
     unknown_data: u8,
+    #[serde(serialize_with = "serialize_hex")]
     sunday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     monday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     tuesday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     wednesday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     thursday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     friday: [u8; 6],
+    #[serde(serialize_with = "serialize_hex")]
     saturday: [u8; 6],
 }
 
@@ -118,17 +134,17 @@ impl fmt::Display for AreaMsg {
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Confort T: {}", (self.temp_comfort + 50)).red()
+                    format!("Confort T: {}", self.temp_comfort).red()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Reduit T: {}", (self.temp_reduced + 50)).green()
+                    format!("Reduit T: {}", self.temp_reduced).green()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Hors gel T: {}", (self.temp_frost + 50)).purple()
+                    format!("Hors gel T: {}", self.temp_frost).purple()
                 )?;
                 write!(f, "\n\t {}", format!("Mode: {m}").yellow())?;
                 write!(f, "\n\t {}", format!("Boost: {}", self.boost).cyan())?;
@@ -141,158 +157,369 @@ impl fmt::Display for AreaMsg {
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Sunday:    {}", format_day(self.sunday)).green()
+                    format!(
+                        "Sunday:    {} {}",
+                        format_day(self.sunday),
+                        to_ranges(&self.sunday).join(" ")
+                    )
+                    .green()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Monday:    {}", format_day(self.monday)).yellow()
+                    format!(
+                        "Monday:    {} {}",
+                        format_day(self.monday),
+                        to_ranges(&self.monday).join(" ")
+                    )
+                    .yellow()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Tuesday:   {}", format_day(self.tuesday)).green()
+                    format!(
+                        "Tuesday:   {} {}",
+                        format_day(self.tuesday),
+                        to_ranges(&self.tuesday).join(" ")
+                    )
+                    .green()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Wednesday: {}", format_day(self.wednesday)).yellow()
+                    format!(
+                        "Wednesday: {} {}",
+                        format_day(self.wednesday),
+                        to_ranges(&self.wednesday).join(" ")
+                    )
+                    .yellow()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Thursday:  {}", format_day(self.thursday)).green()
+                    format!(
+                        "Thursday:  {} {}",
+                        format_day(self.thursday),
+                        to_ranges(&self.thursday).join(" ")
+                    )
+                    .green()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Friday:    {}", format_day(self.friday)).yellow()
+                    format!(
+                        "Friday:    {} {}",
+                        format_day(self.friday),
+                        to_ranges(&self.friday).join(" ")
+                    )
+                    .yellow()
                 )?;
                 write!(
                     f,
                     "\n\t {}",
-                    format!("Saturday:  {}", format_day(self.saturday)).green()
-                )
+                    format!(
+                        "Saturday:  {} {}",
+                        format_day(self.saturday),
+                        to_ranges(&self.saturday).join(" ")
+                    )
+                    .green()
+                )?;
+
+                // the same program as a config section
+                let area = self
+                    .to_area()
+                    .ok()
+                    .map(|area| BTreeMap::from([("area1", area)]));
+                if let Some(Ok(config)) = area.map(|area| toml::to_string(&area)) {
+                    write!(f, "\n    As config:\n{}", config.trim_end())?;
+                }
+                Ok(())
             }
             Err(_) => write!(f, "ERROR"),
         }
     }
 }
 
+const DAYS: [(&str, Weekday); 7] = [
+    ("mon", Weekday::Mon),
+    ("tue", Weekday::Tue),
+    ("wed", Weekday::Wed),
+    ("thu", Weekday::Thu),
+    ("fri", Weekday::Fri),
+    ("sat", Weekday::Sat),
+    ("sun", Weekday::Sun),
+];
+
+impl AreaMsg {
+    /// Message of a zone program, `comfort` is the mode of the current slot
+    /// in auto mode.
+    pub fn new(prog: &config::Area, comfort: bool) -> Result<AreaMsg, ConnectError> {
+        Ok(AreaMsg {
+            cmd: [0xa1, 0x54, 0x00, 0x18],
+            cmd2: [0xa1, 0x54, 0x00, 0x18],
+            len: 0x30,
+            temp_comfort: prog.comfort,
+            temp_reduced: prog.reduced,
+            temp_frost: prog.frost,
+            mode: prog.mode()?,
+            unknow_mode: false,
+            boost: prog.boost,
+            unknown_mode2: 0,
+            unknown_mode3: 1,
+            derogation: prog.comfort_override()?.is_some(),
+            confort: prog.comfort_override()?.unwrap_or(comfort),
+            unknown_data: 0,
+            sunday: prog.sunday()?,
+            monday: prog.monday()?,
+            tuesday: prog.tuesday()?,
+            wednesday: prog.wednesday()?,
+            thursday: prog.thursday()?,
+            friday: prog.friday()?,
+            saturday: prog.saturday()?,
+        })
+    }
+
+    /// Zone program of a message, with one schedule entry per day.
+    pub fn to_area(&self) -> Result<config::Area, ConnectError> {
+        let mode = match self.mode {
+            0x05 => "auto",
+            0x06 => "comfort",
+            0x07 => "reduced",
+            0x08 => "frost",
+            x => return Err(ConnectError::new(&format!("unknown mode: {:02x}", x))),
+        };
+        let r#override = match (self.derogation, self.confort) {
+            (false, _) => "none",
+            (true, true) => "comfort",
+            (true, false) => "reduced",
+        };
+        let schedule = DAYS
+            .iter()
+            .map(|(name, day)| {
+                let ranges = to_ranges(&self.day(*day));
+                (name.to_string(), DaySchedule::Ranges(ranges))
+            })
+            .collect();
+
+        Ok(config::Area {
+            comfort: self.temp_comfort,
+            reduced: self.temp_reduced,
+            frost: self.temp_frost,
+            mode: mode.into(),
+            boost: self.boost,
+            r#override: r#override.into(),
+            override_until: None,
+            profiles: BTreeMap::new(),
+            schedule,
+            monday: vec![],
+            tuesday: vec![],
+            wednesday: vec![],
+            thursday: vec![],
+            friday: vec![],
+            saturday: vec![],
+            sunday: vec![],
+        })
+    }
+
+    /// Comfort slots of a day.
+    pub fn day(&self, day: Weekday) -> [u8; 6] {
+        match day {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        }
+    }
+}
+
 impl Assert for AreaMsg {
     fn assert(&self) -> bool {
         self.cmd == self.cmd2 && self.len == 0x30
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<AreaMsg>(Direction::Request, "a1540018", None, None, "AreaMsg");
+}
+
 pub fn connect_area1(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Config,
 ) -> Result<(Metadata, ()), ConnectError> {
-    rf.set_network_id(Vec::from(config.frisquet()?.network_id()?))?;
-
-    let (_meta, date) = connect_date(rf, config.frisquet()?)?;
-
-    let mut req_id = config.frisquet()?.next_req_id()?;
-    let prog = config.area1()?;
-
-    let curr_mode = if prog.mode_is_auto()? {
-        let slot = date.hour() * 2 + date.minute() / 30;
-        match date.weekday() {
-            1 => prog.monday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            2 => prog.tuesday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            3 => prog.wednesday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            4 => prog.thursday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            5 => prog.friday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            6 => prog.saturday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            7 => prog.sunday()?[(slot / 8) as usize] & (1 << slot % 8) != 0,
-            _ => Err(String::from("invalid weekday"))?,
-        }
-    } else {
-        prog.mode_is_comfort()?
-    };
-
-    let msg = AreaMsg {
-        cmd: [0xa1, 0x54, 0x00, 0x18],
-        cmd2: [0xa1, 0x54, 0x00, 0x18],
-        len: 0x30,
-        temp_comfort: prog.comfort()?,
-        temp_reduced: prog.reduced()?,
-        temp_frost: prog.frost()?,
-        mode: prog.mode()?,
-        unknow_mode: false,
-        boost: prog.boost,
-        unknown_mode2: 0,
-        unknown_mode3: 1,
-        derogation: prog.comfort_override()?.is_some(),
-        confort: prog.comfort_override()?.unwrap_or(curr_mode),
-        unknown_data: 0,
-        sunday: prog.sunday()?,
-        monday: prog.monday()?,
-        tuesday: prog.tuesday()?,
-        wednesday: prog.wednesday()?,
-        thursday: prog.thursday()?,
-        friday: prog.friday()?,
-        saturday: prog.saturday()?,
-    };
+    let prog = config.area1()?.clone();
+    connect_area(rf, config.frisquet()?, &prog)
+}
 
-    println!("{}", msg);
+/// Same as `connect_area1`, but gives up like `try_connect_area`.
+pub fn try_connect_area1(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Config,
+) -> Result<(Metadata, ()), ConnectError> {
+    let prog = config.area1()?.clone();
+    try_connect_area(rf, config.frisquet()?, &prog)
+}
+
+/// Send the zone 1 program, `prog` may differ from the configured one.
+pub fn connect_area(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
+    prog: &config::Area,
+) -> Result<(Metadata, ()), ConnectError> {
+    prog.validate()?;
+    rf.set_network_id(config.network_id()?)?;
 
+    let (_meta, date) = connect_date(rf, config)?;
+    let msg = area_msg(prog, &date)?;
+
+    let mut req_id = config.next_req_id()?;
     let mut retry = 0;
     loop {
-        send_cmd(
-            rf,
-            0x7e, // from
-            0x80, // to
-            config.frisquet()?.association_id()?,
-            req_id,
-            0x08,
-            0x17,
-            &msg,
-        )?;
-
-        match wait_response(
-            rf,
-            config.frisquet()?.association_id()?,
-            req_id,
-            Duration::new(5, 0),
-        )? {
-            Some((meta, _)) => return Ok((meta, ())),
-            None => {
-                retry += 1;
-                req_id += 1;
-                if retry == 3 {
-                    retry = 0;
-                    req_id = config.frisquet()?.next_req_id()?;
-                }
-            }
+        if let Some((meta, _)) = send_area(rf, config, req_id, &msg)? {
+            return Ok((meta, ()));
+        }
+        retry += 1;
+        req_id += 1;
+        if retry == 3 {
+            retry = 0;
+            req_id = config.next_req_id()?;
         }
     }
 }
 
-fn wait_response(
+/// Same as `connect_area`, but an error once the boiler hasn't answered
+/// three requests, so the daemon loop goes on.
+pub fn try_connect_area(
     rf: &mut Box<dyn RFClient>,
-    association_id: u8,
+    config: &mut config::Frisquet,
+    prog: &config::Area,
+) -> Result<(Metadata, ()), ConnectError> {
+    prog.validate()?;
+    rf.set_network_id(config.network_id()?)?;
+
+    let (_meta, date) = try_connect_date(rf, config, Duration::new(5, 0))?;
+    let msg = area_msg(prog, &date)?;
+
+    let req_id = config.next_req_id()?;
+    for retry in 0..3 {
+        if let Some((meta, _)) = send_area(rf, config, req_id.wrapping_add(retry), &msg)? {
+            return Ok((meta, ()));
+        }
+    }
+    Err(ConnectError::new("area1 not acknowledged by the boiler"))
+}
+
+fn area_msg(prog: &config::Area, date: &DateMsg) -> Result<AreaMsg, ConnectError> {
+    let day = date
+        .chrono_weekday()
+        .ok_or(String::from("invalid weekday"))?;
+    let curr_mode = prog.comfort_at(day, date.hour(), date.minute())?;
+
+    let msg = AreaMsg::new(prog, curr_mode)?;
+
+    println!("{}", msg);
+    Ok(msg)
+}
+
+fn send_area(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
     req_id: u8,
-    timeout: Duration,
+    msg: &AreaMsg,
 ) -> Result<Option<(Metadata, ())>, ConnectError> {
-    loop {
-        let payload = match rf.recv_timeout(timeout) {
-            Ok(msg) => msg,
-            Err(e) => {
-                if !e.is_timeout() {
-                    return Err(e.into());
-                }
-                return Ok(None);
-            }
-        };
-        match filter(&payload, 0x80, 0x7e, association_id, req_id)? {
-            Some(payload) => {
-                let (meta, data) = from_bytes::<DropMsg>(&payload)?;
-                println!("RECV {} {}", meta, data);
-                return Ok(Some((meta, ())));
-            }
-            None => {}
+    send_cmd(
+        rf,
+        0x7e, // from
+        0x80, // to
+        config.association_id()?,
+        req_id,
+        0x08,
+        0x17,
+        msg,
+    )?;
+
+    wait_response(rf, config.association_id()?, req_id, Duration::new(5, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn area_msg() -> impl Strategy<Value = AreaMsg> {
+        let setpoint = || (0..=51u8).prop_map(|v| v * 5); // 0.5°C steps
+        (
+            (setpoint(), setpoint(), setpoint(), 0x05..=0x08u8),
+            (any::<bool>(), any::<bool>(), any::<bool>()),
+            any::<[[u8; 6]; 7]>(),
+        )
+            .prop_map(|(temps, (boost, derogation, confort), days)| AreaMsg {
+                cmd: [0xa1, 0x54, 0x00, 0x18],
+                cmd2: [0xa1, 0x54, 0x00, 0x18],
+                len: 0x30,
+                temp_comfort: Setpoint::from_raw(temps.0),
+                temp_reduced: Setpoint::from_raw(temps.1),
+                temp_frost: Setpoint::from_raw(temps.2),
+                mode: temps.3,
+                unknow_mode: false,
+                boost,
+                unknown_mode2: 0,
+                unknown_mode3: 1,
+                derogation,
+                confort,
+                unknown_data: 0,
+                sunday: days[0],
+                monday: days[1],
+                tuesday: days[2],
+                wednesday: days[3],
+                thursday: days[4],
+                friday: days[5],
+                saturday: days[6],
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_area_round_trip(msg in area_msg()) {
+            let area = msg.to_area().unwrap();
+            prop_assert_eq!(AreaMsg::new(&area, msg.confort).unwrap(), msg);
         }
     }
+
+    #[test]
+    fn test_decode_frame() {
+        // auto mode, sunday empty, weekdays 07:00-23:30, saturday 07:00-24:00
+        let payload = hex::decode(
+            "a1540018a154001830a58c23052100000000000000\
+             00c0ffffff7f00c0ffffff7f00c0ffffff7f00c0ffffff7f00c0ffffff7f\
+             00c0ffffffff",
+        )
+        .unwrap();
+        let (_, msg) = AreaMsg::from_bytes((&payload, 0)).unwrap();
+        let area = msg.to_area().unwrap();
+
+        assert_eq!(area.comfort.to_f32(), 21.5);
+        assert_eq!(area.reduced.to_f32(), 19.0);
+        assert_eq!(area.frost.to_f32(), 8.5);
+        assert_eq!(area.mode, "auto");
+        assert_eq!(area.r#override, "none");
+        assert_eq!(
+            area.schedule["mon"],
+            DaySchedule::Ranges(vec!["07:00-23:30".into()])
+        );
+        assert_eq!(
+            area.schedule["sat"],
+            DaySchedule::Ranges(vec!["07:00-24:00".into()])
+        );
+        assert_eq!(area.schedule["sun"], DaySchedule::Ranges(vec![]));
+
+        assert_eq!(area.comfort_at(Weekday::Mon, 7, 0), Ok(true));
+        assert_eq!(area.comfort_at(Weekday::Mon, 23, 30), Ok(false));
+        // invalid boiler clock
+        assert!(area.comfort_at(Weekday::Mon, 24, 0).is_err());
+        assert!(area.comfort_at(Weekday::Mon, 165, 0).is_err());
+        assert!(area.comfort_at(Weekday::Mon, 7, 60).is_err());
+    }
 }
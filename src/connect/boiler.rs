@@ -1,16 +1,23 @@
 use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::Serialize;
 use std::fmt;
 
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::serialize_hex;
+
 use super::Assert;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
 pub struct BoilerMsg {
-    cmd: [u8; 4],  // cmd
+    #[serde(serialize_with = "serialize_hex")]
+    cmd: [u8; 4], // cmd
+    #[serde(serialize_with = "serialize_hex")]
     cmd2: [u8; 4], // re cmd
-    len: u8,       // length
+    len: u8, // length
+    #[serde(serialize_with = "serialize_hex")]
     unknown: [u8; 42],
 }
 
@@ -50,3 +57,7 @@ impl Assert for BoilerMsg {
         self.cmd == self.cmd2 && self.len == 0x2A
     }
 }
+
+pub fn register(registry: &mut Registry) {
+    registry.register::<BoilerMsg>(Direction::Request, "a0f00015", Some(51), None, "BoilerMsg");
+}
@@ -1,8 +1,12 @@
+use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::{Serialize, Serializer};
 use std::fmt;
 
 use crate::config;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::fields::{fmt_fields, serialize_fields, Field, FieldMap};
 use crate::connect::{filter, from_bytes, send_cmd, Cmd, ConnectError, Metadata};
 use crate::rf::RFClient;
 
@@ -20,19 +24,40 @@ impl fmt::Display for Data4Msg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.to_bytes().map(hex::encode) {
             Ok(data) => {
-                write!(f, "{}", data)
+                write!(f, "{}{}", data[0..2].yellow(), data[2..].white())?;
+                write!(f, "\n    Data4Msg")?;
+                fmt_fields(self, f)
             }
             Err(_) => write!(f, "ERROR"),
         }
     }
 }
 
+// nothing decoded yet, fields are only added from captured frames
+impl FieldMap for Data4Msg {
+    const FIELDS: &'static [Field] = &[];
+
+    fn raw(&self) -> &[u8] {
+        &self.items
+    }
+}
+
+impl Serialize for Data4Msg {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_fields(self, serializer)
+    }
+}
+
 impl Assert for Data4Msg {
     fn assert(&self) -> bool {
         true
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<Data4Msg>(Direction::Response, "a0f00015", None, None, "Data4Msg");
+}
+
 pub fn connect_data4(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Frisquet,
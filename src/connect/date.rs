@@ -1,23 +1,34 @@
+use chrono::Weekday;
 use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::Serialize;
 use std::fmt;
+use std::time::{Duration, Instant};
 
 use crate::config;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::serialize_bcd;
 use crate::connect::{filter, from_bytes, send_cmd, Cmd, ConnectError, Metadata};
 use crate::rf::RFClient;
 
 use super::Assert;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
 pub struct DateMsg {
     len: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     year: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     month: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     day: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     hour: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     minute: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     second: u8,
     data: u8,
     weekday: u8,
@@ -61,6 +72,10 @@ impl Assert for DateMsg {
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<DateMsg>(Direction::Response, "a02b0004", None, None, "DateMsg");
+}
+
 impl DateMsg {
     pub fn year(&self) -> u8 {
         (self.year >> 4) * 10 + (self.year & 0x0F)
@@ -75,7 +90,7 @@ impl DateMsg {
         (self.hour >> 4) * 10 + (self.hour & 0x0F)
     }
     pub fn minute(&self) -> u8 {
-        (self.minute >> 4) * 10 + (self.hour & 0x0F)
+        (self.minute >> 4) * 10 + (self.minute & 0x0F)
     }
     pub fn second(&self) -> u8 {
         (self.second >> 4) * 10 + (self.second & 0x0F)
@@ -83,12 +98,60 @@ impl DateMsg {
     pub fn weekday(&self) -> u8 {
         self.weekday
     }
+    /// `weekday` is 1 for monday to 7 for sunday
+    pub fn chrono_weekday(&self) -> Option<Weekday> {
+        Weekday::try_from(self.weekday.checked_sub(1)?).ok()
+    }
 }
 
 pub fn connect_date(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Frisquet,
 ) -> Result<(Metadata, DateMsg), ConnectError> {
+    let req_id = request_date(rf, config)?;
+
+    loop {
+        match filter(&rf.recv()?, 0x80, 0x7e, config.association_id()?, req_id)? {
+            Some(payload) => {
+                let (meta, data) = from_bytes(&payload)?;
+                println!("RECV {} {}", meta, data);
+                return Ok((meta, data));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Same as `connect_date`, but an error if the boiler doesn't answer
+/// within `timeout`.
+pub fn try_connect_date(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
+    timeout: Duration,
+) -> Result<(Metadata, DateMsg), ConnectError> {
+    let req_id = request_date(rf, config)?;
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let payload = match rf.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(payload) => payload,
+            Err(e) if e.is_timeout() => {
+                return Err(ConnectError::new("date not answered by the boiler"))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(payload) = filter(&payload, 0x80, 0x7e, config.association_id()?, req_id)? {
+            let (meta, data) = from_bytes(payload)?;
+            println!("RECV {} {}", meta, data);
+            return Ok((meta, data));
+        }
+    }
+}
+
+fn request_date(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
+) -> Result<u8, ConnectError> {
     rf.set_network_id(Vec::from(config.network_id()?))?;
 
     let req_id = config.next_req_id()?;
@@ -105,15 +168,22 @@ pub fn connect_date(
             data: vec![0xa0, 0x2b, 0x00, 0x04],
         },
     )?;
+    Ok(req_id)
+}
 
-    loop {
-        match filter(&rf.recv()?, 0x80, 0x7e, config.association_id()?, req_id)? {
-            Some(payload) => {
-                let (meta, data) = from_bytes(&payload)?;
-                println!("RECV {} {}", meta, data);
-                return Ok((meta, data));
-            }
-            None => {}
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_fields() {
+        // saturday 2024-10-12 18:45:07
+        let payload = hex::decode("0f2080ba408117082410121845070006").unwrap();
+        let (_meta, msg) = from_bytes::<DateMsg>(&payload).unwrap();
+
+        assert_eq!(msg.hour(), 18);
+        assert_eq!(msg.minute(), 45);
+        assert_eq!(msg.second(), 7);
+        assert_eq!(msg.chrono_weekday(), Some(Weekday::Sat));
     }
 }
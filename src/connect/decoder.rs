@@ -0,0 +1,433 @@
+use chrono::{DateTime, Local};
+use deku::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::connect::{
+    area, boiler, data1, data2, data3, data4, date, holiday, pair, satellite, sensors, sonde,
+    Assert, Metadata,
+};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    pub fn of(meta: &Metadata) -> Direction {
+        if meta.control & 0x80 != 0 {
+            Direction::Response
+        } else {
+            Direction::Request
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Request => write!(f, "=>"),
+            Direction::Response => write!(f, "<="),
+        }
+    }
+}
+
+/// Command id of a request: the first register read for `0x03` and `0x17`
+/// messages (e.g. `79e0001c`), the message type otherwise (e.g. `41`).
+pub fn command_id(meta: &Metadata, payload: &[u8]) -> String {
+    match meta.msg_type {
+        0x03 | 0x17 if payload.len() >= 4 => hex::encode(&payload[0..4]),
+        msg_type => format!("{:02x}", msg_type),
+    }
+}
+
+/// Decoded message, printable as text or JSON.
+pub trait Message: fmt::Display {
+    fn to_json(&self) -> serde_json::Value;
+}
+
+impl<T: fmt::Display + Serialize> Message for T {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+pub trait Decoder {
+    fn name(&self) -> &'static str;
+    /// Decode a full frame, metadata included.
+    fn decode(&self, frame: &[u8]) -> Result<Box<dyn Message>, DekuError>;
+}
+
+struct MsgDecoder<T> {
+    name: &'static str,
+    msg: PhantomData<fn() -> T>,
+}
+
+impl<T> Decoder for MsgDecoder<T>
+where
+    T: for<'a> DekuContainerRead<'a> + Assert + fmt::Display + Serialize + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<Box<dyn Message>, DekuError> {
+        let (_, data) = T::from_bytes((frame.get(7..).unwrap_or_default(), 0))?;
+        if !data.assert() {
+            return Err(DekuError::Assertion(format!("invalid {}", self.name)));
+        }
+        Ok(Box::new(data))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+struct Key {
+    direction: Direction,
+    cmd: String,
+    len: Option<usize>,
+}
+
+struct Entry {
+    from_addr: Option<u8>,
+    decoder: Box<dyn Decoder>,
+}
+
+/// Decoders keyed by direction, command id and payload length (metadata
+/// excluded), optionally restricted to a sender address.
+#[derive(Default)]
+pub struct Registry {
+    decoders: HashMap<Key, Vec<Entry>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Registry with every known message.
+    pub fn with_known_messages() -> Registry {
+        let mut registry = Registry::new();
+        area::register(&mut registry);
+        boiler::register(&mut registry);
+        data1::register(&mut registry);
+        data2::register(&mut registry);
+        data3::register(&mut registry);
+        data4::register(&mut registry);
+        date::register(&mut registry);
+        holiday::register(&mut registry);
+        pair::register(&mut registry);
+        satellite::register(&mut registry);
+        sensors::register(&mut registry);
+        sonde::register(&mut registry);
+        registry
+    }
+
+    /// Register a message type, `len` is the payload length after the
+    /// metadata, `None` for any length.
+    pub fn register<T>(
+        &mut self,
+        direction: Direction,
+        cmd: &str,
+        len: Option<usize>,
+        from_addr: Option<u8>,
+        name: &'static str,
+    ) where
+        T: for<'a> DekuContainerRead<'a> + Assert + fmt::Display + Serialize + 'static,
+    {
+        self.register_decoder(
+            direction,
+            cmd,
+            len,
+            from_addr,
+            Box::new(MsgDecoder::<T> {
+                name,
+                msg: PhantomData,
+            }),
+        )
+    }
+
+    pub fn register_decoder(
+        &mut self,
+        direction: Direction,
+        cmd: &str,
+        len: Option<usize>,
+        from_addr: Option<u8>,
+        decoder: Box<dyn Decoder>,
+    ) {
+        let key = Key {
+            direction,
+            cmd: cmd.into(),
+            len,
+        };
+        self.decoders
+            .entry(key)
+            .or_default()
+            .push(Entry { from_addr, decoder });
+    }
+
+    /// Most specific decoder: exact length before any length, sender address
+    /// before any sender.
+    pub fn find(
+        &self,
+        direction: Direction,
+        cmd: &str,
+        len: usize,
+        from_addr: u8,
+    ) -> Option<&dyn Decoder> {
+        for len in [Some(len), None] {
+            let key = Key {
+                direction,
+                cmd: cmd.into(),
+                len,
+            };
+            if let Some(entries) = self.decoders.get(&key) {
+                let entry = entries
+                    .iter()
+                    .find(|e| e.from_addr == Some(from_addr))
+                    .or(entries.iter().find(|e| e.from_addr.is_none()));
+                if let Some(entry) = entry {
+                    return Some(entry.decoder.as_ref());
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Pairs responses with their request, by association id, request id and
+/// addresses.
+#[derive(Default)]
+pub struct Correlator {
+    inflight: HashMap<(u8, u8, u8, u8), String>,
+}
+
+impl Correlator {
+    pub fn new() -> Correlator {
+        Correlator::default()
+    }
+
+    /// Command id of the frame, for a response the one of its request.
+    pub fn command_id(&mut self, meta: &Metadata, payload: &[u8]) -> Option<String> {
+        match Direction::of(meta) {
+            Direction::Request => {
+                let cmd = command_id(meta, payload);
+                self.inflight.insert(
+                    (
+                        meta.association_id,
+                        meta.request_id,
+                        meta.from_addr,
+                        meta.to_addr,
+                    ),
+                    cmd.clone(),
+                );
+                Some(cmd)
+            }
+            Direction::Response => {
+                let key = (
+                    meta.association_id,
+                    meta.request_id,
+                    meta.to_addr,
+                    meta.from_addr,
+                );
+                // broadcast requests are answered by any device
+                let broadcast = (meta.association_id, meta.request_id, meta.to_addr, 0x00);
+                self.inflight
+                    .remove(&key)
+                    .or_else(|| self.inflight.remove(&broadcast))
+            }
+        }
+    }
+}
+
+pub struct DecodedFrame {
+    pub timestamp: DateTime<Local>,
+    pub meta: Metadata,
+    pub direction: Direction,
+    pub cmd: Option<String>,
+    pub payload: Vec<u8>,
+    /// full frame, metadata included
+    pub raw: Vec<u8>,
+    /// decoded message, `None` if no decoder is registered
+    pub msg: Option<Result<Box<dyn Message>, DekuError>>,
+    pub name: Option<&'static str>,
+}
+
+/// Decode a stream of frames, correlating responses with their requests.
+pub struct Session {
+    registry: Registry,
+    correlator: Correlator,
+}
+
+impl Session {
+    pub fn new(registry: Registry) -> Session {
+        Session {
+            registry,
+            correlator: Correlator::new(),
+        }
+    }
+
+    pub fn decode(&mut self, frame: &[u8]) -> Result<DecodedFrame, DekuError> {
+        let (_, meta) = Metadata::from_bytes((frame, 0))?;
+        let payload = frame.get(7..).unwrap_or_default().to_vec();
+        let direction = Direction::of(&meta);
+        let cmd = self.correlator.command_id(&meta, &payload);
+
+        let decoder = cmd.as_ref().and_then(|cmd| {
+            self.registry
+                .find(direction, cmd, payload.len(), meta.from_addr)
+        });
+
+        Ok(DecodedFrame {
+            timestamp: Local::now(),
+            direction,
+            cmd,
+            payload,
+            name: decoder.map(|d| d.name()),
+            msg: decoder.map(|d| d.decode(frame)),
+            raw: frame.to_vec(),
+            meta,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFrame<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    meta: &'a Metadata,
+    direction: Direction,
+    cmd: Option<&'a str>,
+    raw: String,
+    message: Option<&'static str>,
+    data: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+impl DecodedFrame {
+    /// One JSON object per frame: metadata, raw hex and decoded fields.
+    pub fn to_json(&self) -> serde_json::Value {
+        let (data, error) = match &self.msg {
+            Some(Ok(msg)) => (Some(msg.to_json()), None),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+        let frame = JsonFrame {
+            timestamp: self.timestamp.to_rfc3339(),
+            meta: &self.meta,
+            direction: self.direction,
+            cmd: self.cmd.as_deref(),
+            raw: hex::encode(&self.raw),
+            message: self.name,
+            data,
+            error,
+        };
+        serde_json::to_value(frame).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.direction, self.meta)?;
+        match &self.msg {
+            Some(Ok(msg)) => write!(f, "{}", msg),
+            Some(Err(e)) => write!(
+                f,
+                "{} err: {} - {}",
+                self.name.unwrap_or("?"),
+                e,
+                hex::encode(&self.payload)
+            ),
+            None => write!(f, "{}", hex::encode(&self.payload)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlate_and_decode() {
+        let mut session = Session::new(Registry::with_known_messages());
+
+        // sonde temperature, request then response
+        let request = session
+            .decode(&hex::decode("118020ba4001179c540004a029000102005c").unwrap())
+            .unwrap();
+        assert_eq!(request.direction, Direction::Request);
+        assert_eq!(request.cmd.as_deref(), Some("9c540004"));
+        assert_eq!(request.name, Some("SetExternalTemperatureMsg"));
+        assert!(matches!(request.msg, Some(Ok(_))));
+
+        // response from another device is not correlated
+        let other = session
+            .decode(&hex::decode("0f7e80ba408117082304051131172803").unwrap())
+            .unwrap();
+        assert_eq!(other.cmd, None);
+        assert!(other.msg.is_none());
+
+        let response = session
+            .decode(&hex::decode("0f2080ba408117082304051131172803").unwrap())
+            .unwrap();
+        assert_eq!(response.direction, Direction::Response);
+        assert_eq!(response.cmd.as_deref(), Some("9c540004"));
+        assert_eq!(response.name, Some("SetExternalTemperatureReplyMsg"));
+        assert!(matches!(response.msg, Some(Ok(_))));
+    }
+
+    #[test]
+    fn test_broadcast_association() {
+        let mut session = Session::new(Registry::with_known_messages());
+
+        let request = session
+            .decode(&hex::decode("0b008012d402410412345678").unwrap())
+            .unwrap();
+        assert_eq!(request.name, Some("AssociationMsg"));
+
+        let response = session
+            .decode(&hex::decode("0a800912d4824101270002").unwrap())
+            .unwrap();
+        assert_eq!(response.cmd.as_deref(), Some("41"));
+        assert_eq!(response.name, Some("AssociationCmd"));
+        assert!(matches!(response.msg, Some(Ok(_))));
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let mut session = Session::new(Registry::with_known_messages());
+        // sensors request then a truncated response
+        session
+            .decode(&hex::decode("0a807e12d4010379e0001c").unwrap())
+            .unwrap();
+        let response = session
+            .decode(&hex::decode("0a7e8012d481033800c8").unwrap())
+            .unwrap();
+
+        assert_eq!(response.name, Some("SensorsMsg"));
+        assert!(matches!(response.msg, Some(Err(_))));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut session = Session::new(Registry::with_known_messages());
+        let request = session
+            .decode(&hex::decode("118020ba4001179c540004a029000102005c").unwrap())
+            .unwrap();
+
+        let json = request.to_json();
+        assert_eq!(json["from_addr"], 0x20);
+        assert_eq!(json["to_addr"], 0x80);
+        assert_eq!(json["request_id"], 0x40);
+        assert_eq!(json["msg_type"], 0x17);
+        assert_eq!(json["direction"], "request");
+        assert_eq!(json["cmd"], "9c540004");
+        assert_eq!(json["raw"], "118020ba4001179c540004a029000102005c");
+        assert_eq!(json["message"], "SetExternalTemperatureMsg");
+        assert_eq!(json["data"]["temperature"], 9.2);
+        assert!(json["error"].is_null());
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use deku::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::connect::decoder::{Correlator, Direction};
+use crate::connect::pair::{AssociationCmd, AssociationMsg};
+use crate::connect::Metadata;
+
+/// Role of the known radio addresses, `None` for unknown ones.
+pub fn role(addr: u8) -> Option<&'static str> {
+    match addr {
+        0x08 => Some("satellite-z1"),
+        0x09 => Some("satellite-z2"),
+        0x0a => Some("satellite-z3"),
+        0x20 => Some("sonde"),
+        0x7e => Some("connect"),
+        0x80 => Some("boiler"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DeviceInfo {
+    pub frames: u32,
+    pub association_ids: BTreeSet<u8>,
+    pub first_request_id: Option<u8>,
+    pub last_request_id: Option<u8>,
+    /// request id increments between two requests, and their count
+    pub request_id_steps: BTreeMap<u8, u32>,
+    /// firmware version, from the association response
+    pub version: Option<[u8; 4]>,
+    /// network id announced for association
+    pub network_id: Option<[u8; 4]>,
+    pub msg_types: BTreeSet<u8>,
+    pub commands: BTreeSet<String>,
+    pub last_seen: Option<DateTime<Local>>,
+    /// dBm of the last frame
+    pub rssi: Option<i16>,
+}
+
+impl DeviceInfo {
+    /// Most common request id increment.
+    pub fn request_id_step(&self) -> Option<u8> {
+        self.request_id_steps
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(step, _)| *step)
+    }
+}
+
+/// Devices seen on the network, by address.
+#[derive(Default)]
+pub struct Inventory {
+    correlator: Correlator,
+    devices: BTreeMap<u8, DeviceInfo>,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory::default()
+    }
+
+    pub fn push(
+        &mut self,
+        frame: &[u8],
+        rssi: Option<i16>,
+        now: DateTime<Local>,
+    ) -> Result<(), DekuError> {
+        let (_, meta) = Metadata::from_bytes((frame, 0))?;
+        let payload = frame.get(7..).unwrap_or_default();
+        let direction = Direction::of(&meta);
+        let cmd = self.correlator.command_id(&meta, payload);
+
+        let device = self.devices.entry(meta.from_addr).or_default();
+        device.frames += 1;
+        device.association_ids.insert(meta.association_id);
+        device.msg_types.insert(meta.msg_type);
+        device.last_seen = Some(now);
+        if rssi.is_some() {
+            device.rssi = rssi;
+        }
+
+        match direction {
+            Direction::Request => {
+                if let Some(last) = device.last_request_id {
+                    let step = meta.request_id.wrapping_sub(last);
+                    *device.request_id_steps.entry(step).or_default() += 1;
+                }
+                device.first_request_id.get_or_insert(meta.request_id);
+                device.last_request_id = Some(meta.request_id);
+            }
+            Direction::Response => {}
+        }
+
+        match (direction, cmd.as_deref()) {
+            (Direction::Request, Some("41")) => {
+                if let Ok((_, msg)) = AssociationMsg::from_bytes((payload, 0)) {
+                    device.network_id = Some(msg.network_id());
+                }
+            }
+            (Direction::Response, Some("41")) => {
+                if let Ok((_, msg)) = AssociationCmd::from_bytes((payload, 0)) {
+                    device.version = Some(msg.version());
+                }
+            }
+            _ => {}
+        }
+        if let Some(cmd) = cmd {
+            device.commands.insert(cmd);
+        }
+
+        // the recipient is live only once it sends, but it is known
+        if meta.to_addr != 0x00 {
+            self.devices.entry(meta.to_addr).or_default();
+        }
+        Ok(())
+    }
+
+    pub fn devices(&self) -> &BTreeMap<u8, DeviceInfo> {
+        &self.devices
+    }
+
+    /// Addresses without a known role.
+    pub fn unknown(&self) -> Vec<u8> {
+        self.devices
+            .keys()
+            .copied()
+            .filter(|addr| role(*addr).is_none())
+            .collect()
+    }
+}
+
+impl fmt::Display for Inventory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for (addr, d) in &self.devices {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+
+            let title = format!("0x{:02x} {}", addr, role(*addr).unwrap_or("UNKNOWN"));
+            match (role(*addr), d.frames) {
+                (None, _) => write!(f, "{}", title.red())?,
+                (_, 0) => write!(f, "{} (never sent)", title.yellow())?,
+                _ => write!(f, "{}", title.cyan())?,
+            }
+            if d.frames == 0 {
+                continue;
+            }
+
+            let ids: Vec<String> = d
+                .association_ids
+                .iter()
+                .map(|id| format!("{:02x}", id))
+                .collect();
+            write!(f, "\n\tframes: {}", d.frames)?;
+            write!(f, "\n\tassociation id: {}", ids.join(", "))?;
+            if let (Some(first), Some(last)) = (d.first_request_id, d.last_request_id) {
+                write!(f, "\n\trequest id: {:02x}..{:02x}", first, last)?;
+                if let Some(step) = d.request_id_step() {
+                    write!(f, " (step {})", step)?;
+                }
+            }
+            if let Some(version) = d.version {
+                write!(f, "\n\tversion: {}", hex::encode(version))?;
+            }
+            if let Some(network_id) = d.network_id {
+                write!(f, "\n\tnetwork id: {}", hex::encode(network_id))?;
+            }
+            let types: Vec<String> = d.msg_types.iter().map(|t| format!("{:02x}", t)).collect();
+            write!(f, "\n\tmessage types: {}", types.join(", "))?;
+            if !d.commands.is_empty() {
+                let commands: Vec<&str> = d.commands.iter().map(String::as_str).collect();
+                write!(f, "\n\tcommands: {}", commands.join(", "))?;
+            }
+            if let Some(rssi) = d.rssi {
+                write!(f, "\n\tRSSI: {} dBm", rssi)?;
+            }
+            if let Some(last_seen) = d.last_seen {
+                write!(
+                    f,
+                    "\n\tlast seen: {}",
+                    last_seen.format("%Y-%m-%d %H:%M:%S")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(inventory: &mut Inventory, frame: &str, rssi: Option<i16>) {
+        inventory
+            .push(&hex::decode(frame).unwrap(), rssi, Local::now())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_association() {
+        let mut inventory = Inventory::new();
+        push(&mut inventory, "0b008012d402410412345678", Some(-60));
+        push(&mut inventory, "0a800912d4824101270002", Some(-75));
+
+        let boiler = &inventory.devices()[&0x80];
+        assert_eq!(boiler.network_id, Some([0x12, 0x34, 0x56, 0x78]));
+        assert_eq!(boiler.rssi, Some(-60));
+
+        let satellite = &inventory.devices()[&0x09];
+        assert_eq!(satellite.version, Some([0x01, 0x27, 0x00, 0x02]));
+        assert_eq!(satellite.association_ids, BTreeSet::from([0x12]));
+        assert_eq!(satellite.rssi, Some(-75));
+        assert!(inventory.unknown().is_empty());
+    }
+
+    #[test]
+    fn test_request_ids_and_unknown() {
+        let mut inventory = Inventory::new();
+        for req_id in [0x10, 0x14, 0x18, 0x20] {
+            push(
+                &mut inventory,
+                &format!("0a807e12{:02x}010379e0001c", req_id),
+                None,
+            );
+        }
+        push(&mut inventory, "0a80331200010379e0001c", None);
+
+        let connect = &inventory.devices()[&0x7e];
+        assert_eq!(connect.first_request_id, Some(0x10));
+        assert_eq!(connect.last_request_id, Some(0x20));
+        assert_eq!(connect.request_id_step(), Some(4));
+        assert!(connect.commands.contains("79e0001c"));
+        assert_eq!(connect.rssi, None);
+        assert_eq!(inventory.unknown(), vec![0x33]);
+    }
+}
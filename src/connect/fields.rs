@@ -0,0 +1,254 @@
+use colored::Colorize;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+// Field offsets are byte offsets in the returned payload, length byte
+// excluded.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    /// big endian unsigned number, multiplied by `scale`
+    Unsigned,
+    /// big endian two's complement number, multiplied by `scale`
+    Signed,
+    /// single bit of the byte at offset, 0 is the least significant
+    Flag(u8),
+    /// raw value, displayed as hex (error codes, states)
+    Code,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub offset: usize,
+    pub width: usize,
+    pub kind: Kind,
+    pub scale: f32,
+    pub unit: &'static str,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Value {
+    Flag(bool),
+    Number(f32),
+    Code(u32),
+}
+
+impl Field {
+    pub const fn unsigned(
+        name: &'static str,
+        offset: usize,
+        width: usize,
+        scale: f32,
+        unit: &'static str,
+    ) -> Field {
+        Field {
+            name,
+            offset,
+            width,
+            kind: Kind::Unsigned,
+            scale,
+            unit,
+        }
+    }
+
+    pub const fn signed(
+        name: &'static str,
+        offset: usize,
+        width: usize,
+        scale: f32,
+        unit: &'static str,
+    ) -> Field {
+        Field {
+            name,
+            offset,
+            width,
+            kind: Kind::Signed,
+            scale,
+            unit,
+        }
+    }
+
+    pub const fn flag(name: &'static str, offset: usize, bit: u8) -> Field {
+        Field {
+            name,
+            offset,
+            width: 1,
+            kind: Kind::Flag(bit),
+            scale: 1.0,
+            unit: "",
+        }
+    }
+
+    pub const fn code(name: &'static str, offset: usize, width: usize) -> Field {
+        Field {
+            name,
+            offset,
+            width,
+            kind: Kind::Code,
+            scale: 1.0,
+            unit: "",
+        }
+    }
+
+    /// Decode the field from `data`, `None` if the payload is too short.
+    pub fn decode(&self, data: &[u8]) -> Option<Value> {
+        let bytes = data.get(self.offset..self.offset + self.width)?;
+        let raw = bytes.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+
+        Some(match self.kind {
+            Kind::Unsigned => Value::Number(raw as f32 * self.scale),
+            Kind::Signed => {
+                let shift = 32 - 8 * self.width as u32;
+                let raw = ((raw << shift) as i32) >> shift;
+                Value::Number(raw as f32 * self.scale)
+            }
+            Kind::Flag(bit) => Value::Flag(raw & (1 << bit) != 0),
+            Kind::Code => Value::Code(raw),
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Flag(true) => write!(f, "on"),
+            Value::Flag(false) => write!(f, "off"),
+            Value::Number(v) => write!(f, "{:.1}", v),
+            Value::Code(v) => write!(f, "{:#06x}", v),
+        }
+    }
+}
+
+/// Messages whose payload is described by a field map.
+pub trait FieldMap {
+    const FIELDS: &'static [Field];
+
+    /// payload bytes, the field offsets refer to
+    fn raw(&self) -> &[u8];
+
+    fn fields(&self) -> Vec<(&'static Field, Value)> {
+        Self::FIELDS
+            .iter()
+            .filter_map(|field| field.decode(self.raw()).map(|value| (field, value)))
+            .collect()
+    }
+
+    /// bytes not fully covered by fields, with their offset, flags only
+    /// cover their bit
+    fn unknown(&self) -> Vec<(usize, u8)> {
+        self.raw()
+            .iter()
+            .enumerate()
+            .filter(|(n, _)| {
+                let mut bits = 0u8;
+                for field in Self::FIELDS {
+                    if !(field.offset..field.offset + field.width).contains(n) {
+                        continue;
+                    }
+                    match field.kind {
+                        Kind::Flag(bit) => bits |= 1 << bit,
+                        _ => return false,
+                    }
+                }
+                bits != 0xff
+            })
+            .map(|(n, b)| (n, *b))
+            .collect()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Flag(v) => serializer.serialize_bool(*v),
+            // shortest decimal representation, 0.1 not 0.10000000149
+            Value::Number(v) => serializer.serialize_f64(v.to_string().parse().unwrap_or_default()),
+            Value::Code(v) => serializer.collect_str(&format_args!("{:#06x}", v)),
+        }
+    }
+}
+
+/// Decoded fields by name, and the raw payload as hex.
+pub fn serialize_fields<T: FieldMap, S: Serializer>(
+    msg: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let fields = msg.fields();
+    let mut map = serializer.serialize_map(Some(fields.len() + 1))?;
+    for (field, value) in fields {
+        map.serialize_entry(field.name, &value)?;
+    }
+    map.serialize_entry("raw", &hex::encode(msg.raw()))?;
+    map.end()
+}
+
+pub fn fmt_fields<T: FieldMap>(msg: &T, f: &mut fmt::Formatter) -> fmt::Result {
+    for (field, value) in msg.fields() {
+        let line = format!("{}: {} {}", field.name, value, field.unit);
+        write!(f, "\n\t {}", line.trim_end().green())?;
+    }
+    let unknown = msg.unknown();
+    if !unknown.is_empty() {
+        write!(
+            f,
+            "\n\t {}",
+            format!("Unknown: {} bytes", unknown.len()).white()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let data = [0xff, 0x38, 0x00, 0x0f, 0x81];
+
+        assert_eq!(
+            Field::signed("t", 0, 2, 0.1, "C").decode(&data),
+            Some(Value::Number(-20.0))
+        );
+        assert_eq!(
+            Field::unsigned("p", 2, 2, 0.1, "bar").decode(&data),
+            Some(Value::Number(1.5))
+        );
+        assert_eq!(
+            Field::flag("b0", 4, 0).decode(&data),
+            Some(Value::Flag(true))
+        );
+        assert_eq!(
+            Field::flag("b1", 4, 1).decode(&data),
+            Some(Value::Flag(false))
+        );
+        assert_eq!(
+            Field::code("e", 3, 2).decode(&data),
+            Some(Value::Code(0x0f81))
+        );
+        assert_eq!(Field::code("e", 4, 2).decode(&data), None);
+    }
+
+    struct Flags([u8; 3]);
+
+    impl FieldMap for Flags {
+        const FIELDS: &'static [Field] = &[
+            Field::code("c", 0, 1),
+            Field::flag("f0", 1, 0),
+            Field::flag("f1", 1, 1),
+        ];
+
+        fn raw(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    #[test]
+    fn test_unknown() {
+        // the other bits of a flag byte stay reachable
+        let msg = Flags([0x01, 0x83, 0x05]);
+        assert_eq!(msg.unknown(), vec![(1, 0x83), (2, 0x05)]);
+        assert_eq!(msg.fields().len(), 3);
+    }
+}
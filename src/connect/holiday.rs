@@ -1,10 +1,17 @@
+use chrono::NaiveDateTime;
 use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::{Serialize, Serializer};
 use std::fmt;
+use std::time::Duration;
 
 use crate::config;
-use crate::connect::{ConnectError, Metadata};
+use crate::connect::data4::connect_data4;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::fields::FieldMap;
+use crate::connect::serialize_hex;
+use crate::connect::{send_cmd, wait_response, ConnectError, Metadata};
 use crate::rf::RFClient;
 
 use super::Assert;
@@ -13,15 +20,21 @@ use super::Assert;
 // a0f00015 a0f00015 2a 00000000000000000000000000000000  dd009a3c0000000000993233303435303939353330303131ffff
 // a0f00015a0f00015  2a e09065a100000000321065a300000000  dd009a3c0000000000993233303435303939353330303131ffff
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
 pub struct HolidayMsg {
-    cmd: [u8; 4],  // cmd
+    #[serde(serialize_with = "serialize_hex")]
+    cmd: [u8; 4], // cmd
+    #[serde(serialize_with = "serialize_hex")]
     cmd2: [u8; 4], // re cmd
     len: u8,
+    #[serde(serialize_with = "serialize_holiday_date")]
     start: [u8; 8],
+    #[serde(serialize_with = "serialize_holiday_date")]
     end: [u8; 8],
+    #[serde(serialize_with = "serialize_holiday_date")]
     date1: [u8; 8],
+    #[serde(serialize_with = "serialize_hex")]
     data: [u8; 18], // data
 }
 
@@ -53,8 +66,13 @@ impl fmt::Display for HolidayMsg {
                     format!("Cmd2: {}", hex::encode(self.cmd2)).magenta()
                 )?;
                 write!(f, "\n\t {}", format!("Length: {:0x}", self.len).yellow())?;
-                write!(f, "\n\t {}", format!("Start: {}", (self.start())).red())?;
-                write!(f, "\n\t {}", format!("End: {}", (self.end())).green())?;
+                match self.period() {
+                    Some((start, end)) => {
+                        write!(f, "\n\t {}", format!("Start: {}", start).red())?;
+                        write!(f, "\n\t {}", format!("End: {}", end).green())?;
+                    }
+                    None => write!(f, "\n\t {}", "Period: none".red())?,
+                }
                 write!(f, "\n\t {}", format!("Date1: {}", (self.date1())).cyan())
             }
             Err(_) => write!(f, "ERROR"),
@@ -68,33 +86,135 @@ impl Assert for HolidayMsg {
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<HolidayMsg>(
+        Direction::Request,
+        "a0f00015",
+        Some(51),
+        Some(0x7e),
+        "HolidayMsg",
+    );
+}
+
 impl HolidayMsg {
-    fn start(&self) -> chrono::NaiveDateTime {
-        let secs: i64 = (((self.start[2] as u32) << 24)
-            + ((self.start[3] as u32) << 16)
-            + ((self.start[0] as u32) << 8)
-            + self.start[1] as u32) as i64;
-        chrono::NaiveDateTime::from_timestamp_opt(secs, 0).unwrap()
+    /// Holiday block with a new period, `None` to clear it, the other bytes
+    /// as read from the boiler with `connect_data4`.
+    pub fn new(
+        current: &[u8],
+        period: Option<(NaiveDateTime, NaiveDateTime)>,
+    ) -> Result<HolidayMsg, ConnectError> {
+        if current.len() != 0x2a {
+            Err(format!("invalid holiday block: {}", hex::encode(current)))?;
+        }
+        let mut data = vec![0xa0, 0xf0, 0x00, 0x15, 0xa0, 0xf0, 0x00, 0x15, 0x2a];
+        data.extend_from_slice(current);
+        let (_, mut msg) = HolidayMsg::from_bytes((&data, 0))?;
+        (msg.start, msg.end) = match period {
+            Some((start, end)) => (to_holiday_date(start)?, to_holiday_date(end)?),
+            None => ([0; 8], [0; 8]),
+        };
+        Ok(msg)
     }
-    fn end(&self) -> chrono::NaiveDateTime {
-        let secs: i64 = (((self.end[2] as u32) << 24)
-            + ((self.end[3] as u32) << 16)
-            + ((self.end[0] as u32) << 8)
-            + self.end[1] as u32) as i64;
-        chrono::NaiveDateTime::from_timestamp_opt(secs, 0).unwrap()
+
+    /// Holiday period, `None` when cleared.
+    pub fn period(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        match (self.start, self.end) {
+            ([0, 0, 0, 0, ..], [0, 0, 0, 0, ..]) => None,
+            _ => Some((self.start(), self.end())),
+        }
+    }
+
+    fn start(&self) -> NaiveDateTime {
+        holiday_date(&self.start)
     }
-    fn date1(&self) -> chrono::NaiveDateTime {
-        let secs: i64 = (((self.date1[2] as u32) << 24)
-            + ((self.date1[3] as u32) << 16)
-            + ((self.date1[0] as u32) << 8)
-            + self.date1[1] as u32) as i64;
-        chrono::NaiveDateTime::from_timestamp_opt(secs, 0).unwrap()
+    fn end(&self) -> NaiveDateTime {
+        holiday_date(&self.end)
     }
+    fn date1(&self) -> NaiveDateTime {
+        holiday_date(&self.date1)
+    }
+}
+
+// seconds since epoch, as 16 bits words in little endian order
+fn holiday_date(date: &[u8; 8]) -> NaiveDateTime {
+    let secs: i64 = (((date[2] as u32) << 24)
+        + ((date[3] as u32) << 16)
+        + ((date[0] as u32) << 8)
+        + date[1] as u32) as i64;
+    NaiveDateTime::from_timestamp_opt(secs, 0).unwrap()
+}
+
+fn to_holiday_date(date: NaiveDateTime) -> Result<[u8; 8], ConnectError> {
+    let secs = u32::try_from(date.and_utc().timestamp())
+        .map_err(|_| format!("invalid holiday date: {}", date))?;
+    let [b0, b1, b2, b3] = secs.to_be_bytes();
+    Ok([b2, b3, b0, b1, 0, 0, 0, 0])
+}
+
+fn serialize_holiday_date<S: Serializer>(date: &[u8; 8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&holiday_date(date))
 }
 
+/// Set the boiler holiday period, `None` to clear it. The block is read
+/// first and written back with the new dates.
+// the write request mirrors the frames sent by the connect, not confirmed
 pub fn connect_holiday(
-    _rf: &mut Box<dyn RFClient>,
-    _config: &mut config::Config,
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
+    period: Option<(NaiveDateTime, NaiveDateTime)>,
 ) -> Result<(Metadata, ()), ConnectError> {
-    Err("unimplemented".to_string())?
+    let (_meta, current) = connect_data4(rf, config)?;
+    let msg = HolidayMsg::new(current.raw(), period)?;
+
+    println!("{}", msg);
+
+    // not acknowledged if the frame is wrong, give up after a few attempts
+    let req_id = config.next_req_id()?;
+    for retry in 0..3 {
+        let req_id = req_id.wrapping_add(retry);
+        send_cmd(
+            rf,
+            0x7e, // from
+            0x80, // to
+            config.association_id()?,
+            req_id,
+            0x08,
+            0x17,
+            &msg,
+        )?;
+
+        if let Some((meta, _)) =
+            wait_response(rf, config.association_id()?, req_id, Duration::new(5, 0))?
+        {
+            return Ok((meta, ()));
+        }
+    }
+    Err(ConnectError::new(
+        "holiday period not acknowledged by the boiler",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: &str = "a0f00015a0f000152ae09065a100000000321065a300000000\
+                         dd009a3c0000000000993233303435303939353330303131ffff";
+
+    #[test]
+    fn test_holiday_period() {
+        let payload = hex::decode(FRAME).unwrap();
+        let (_, msg) = HolidayMsg::from_bytes((&payload, 0)).unwrap();
+        let (start, end) = msg.period().unwrap();
+        assert_eq!(start.to_string(), "2024-01-13 01:00:00");
+
+        // same period, same frame
+        let rebuilt = HolidayMsg::new(&payload[9..], Some((start, end))).unwrap();
+        assert_eq!(rebuilt, msg);
+
+        let cleared = HolidayMsg::new(&payload[9..], None).unwrap();
+        assert_eq!(cleared.period(), None);
+        assert_eq!(cleared.date1, msg.date1);
+        assert_eq!(cleared.data, msg.data);
+    }
 }
@@ -1,11 +1,14 @@
 use deku::prelude::*;
+use serde::{Serialize, Serializer};
 use std::error;
 use std::fmt;
 use std::fmt::{Debug, Display};
+use std::time::Duration;
 
 use crate::config::ConfigError;
 use crate::rf::{RFClient, RecvError, RecvTimeoutError, SendError};
 
+pub mod analyze;
 pub mod area;
 pub mod boiler;
 pub mod data1;
@@ -13,13 +16,19 @@ pub mod data2;
 pub mod data3;
 pub mod data4;
 pub mod date;
+pub mod decoder;
+pub mod discover;
+pub mod fields;
 pub mod holiday;
 pub mod pair;
 pub mod promiscuous;
+pub mod satellite;
 pub mod sensors;
 pub mod sonde;
+pub mod temperature;
+pub mod tracker;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite, Clone)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Clone, Serialize)]
 #[deku(endian = "big")]
 pub struct Metadata {
     pub length: u8,
@@ -133,6 +142,31 @@ pub fn filter(
     Ok(Some(payload))
 }
 
+/// Wait for the boiler response to a connect request, `None` on timeout.
+pub fn wait_response(
+    rf: &mut Box<dyn RFClient>,
+    association_id: u8,
+    req_id: u8,
+    timeout: Duration,
+) -> Result<Option<(Metadata, ())>, ConnectError> {
+    loop {
+        let payload = match rf.recv_timeout(timeout) {
+            Ok(msg) => msg,
+            Err(e) => {
+                if !e.is_timeout() {
+                    return Err(e.into());
+                }
+                return Ok(None);
+            }
+        };
+        if let Some(payload) = filter(&payload, 0x80, 0x7e, association_id, req_id)? {
+            let (meta, data) = from_bytes::<DropMsg>(payload)?;
+            println!("RECV {} {}", meta, data);
+            return Ok(Some((meta, ())));
+        }
+    }
+}
+
 pub fn from_bytes<'a, T>(payload: &'a Vec<u8>) -> Result<(Metadata, T), DekuError>
 where
     T: DekuContainerRead<'a> + Assert,
@@ -158,6 +192,19 @@ fn format_day(data: [u8; 6]) -> String {
     out
 }
 
+pub fn serialize_hex<T, S>(data: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: Serializer,
+{
+    serializer.serialize_str(&hex::encode(data))
+}
+
+/// BCD encoded value, e.g. 0x23 is 23
+pub fn serialize_bcd<S: Serializer>(value: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u8((value >> 4) * 10 + (value & 0x0F))
+}
+
 pub trait Assert {
     fn assert(&self) -> bool;
 }
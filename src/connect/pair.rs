@@ -3,23 +3,27 @@ use std::time::Duration;
 
 use deku::prelude::*;
 use hex;
+use serde::Serialize;
 
-use crate::connect::{from_bytes, send_cmd, ConnectError, Metadata};
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::{from_bytes, send_cmd, serialize_hex, ConnectError, Metadata};
 
 use crate::rf::RFClient;
 
 use super::Assert;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
-struct AssociationCmd {
+pub struct AssociationCmd {
+    #[serde(serialize_with = "serialize_hex")]
     version: [u8; 4],
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
-struct AssociationMsg {
+pub struct AssociationMsg {
     len: u8,
+    #[serde(serialize_with = "serialize_hex")]
     network_id: [u8; 4],
 }
 
@@ -44,6 +48,19 @@ impl fmt::Display for AssociationMsg {
     }
 }
 
+impl AssociationCmd {
+    /// Firmware version of the device answering the association.
+    pub fn version(&self) -> [u8; 4] {
+        self.version
+    }
+}
+
+impl AssociationMsg {
+    pub fn network_id(&self) -> [u8; 4] {
+        self.network_id
+    }
+}
+
 impl Assert for AssociationMsg {
     fn assert(&self) -> bool {
         self.len as usize == self.network_id.len() // length is expected to represent the network_id length
@@ -57,6 +74,11 @@ impl Assert for AssociationCmd {
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<AssociationMsg>(Direction::Request, "41", None, None, "AssociationMsg");
+    registry.register::<AssociationCmd>(Direction::Response, "41", None, None, "AssociationCmd");
+}
+
 pub fn connect_association(
     rf: &mut Box<dyn RFClient>,
     from: u8,
@@ -0,0 +1,150 @@
+use deku::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::config;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::serialize_hex;
+use crate::connect::sonde::SetExternalTemperatureReplyMsg;
+use crate::connect::temperature::Celsius;
+use crate::connect::{filter, from_bytes, send_cmd, Assert, ConnectError, Metadata};
+use crate::rf::RFClient;
+
+// Like the sonde, a satellite reads 4 words at 0x9c54 (boiler date) and
+// writes its zone registers: ambient temperature then setpoint.
+const ZONE_REGISTERS: [[u8; 2]; 3] = [[0xa0, 0x2f], [0xa0, 0x34], [0xa0, 0x39]];
+
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
+#[deku(endian = "big")]
+pub struct SetZoneTemperatureMsg {
+    #[serde(serialize_with = "serialize_hex")]
+    data: [u8; 9],
+    ambient: Celsius,
+    setpoint: Celsius,
+}
+
+impl fmt::Display for SetZoneTemperatureMsg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = self.to_bytes().map(hex::encode).unwrap_or("ERROR".into());
+        write!(f, "{}\n    SetZoneTemperatureMsg", data)?;
+        write!(f, "\n\tAmbient: {}", self.ambient)?;
+        write!(f, "\n\tSetpoint: {}", self.setpoint)
+    }
+}
+
+impl Assert for SetZoneTemperatureMsg {
+    fn assert(&self) -> bool {
+        self.data[8] == 4 // two words written
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register::<SetZoneTemperatureMsg>(
+        Direction::Request,
+        "9c540004",
+        Some(13),
+        None,
+        "SetZoneTemperatureMsg",
+    );
+}
+
+impl SetZoneTemperatureMsg {
+    pub fn new(zone: u8, ambient: Celsius, setpoint: Celsius) -> Result<Self, ConnectError> {
+        let register = zone_register(zone)?;
+        Ok(SetZoneTemperatureMsg {
+            data: [
+                0x9c,
+                0x54,
+                0x00,
+                0x04,
+                register[0],
+                register[1],
+                0x00,
+                0x02,
+                0x04,
+            ],
+            ambient,
+            setpoint,
+        })
+    }
+}
+
+/// Satellite radio address of a zone, 1 to 3.
+pub fn zone_addr(zone: u8) -> Result<u8, ConnectError> {
+    match zone {
+        1..=3 => Ok(0x08 + zone - 1),
+        _ => Err(ConnectError::new(&format!("invalid zone: {}", zone))),
+    }
+}
+
+fn zone_register(zone: u8) -> Result<[u8; 2], ConnectError> {
+    zone_addr(zone)?;
+    Ok(ZONE_REGISTERS[(zone - 1) as usize])
+}
+
+pub fn send_zone_temperature(
+    rf: &mut Box<dyn RFClient>,
+    config: &mut config::Frisquet,
+    zone: u8,
+    ambient: Celsius,
+    setpoint: Celsius,
+) -> Result<(Metadata, SetExternalTemperatureReplyMsg), ConnectError> {
+    rf.set_network_id(config.network_id()?)?;
+
+    let from = zone_addr(zone)?;
+    let req_id = config.next_req_id()?;
+
+    send_cmd(
+        rf,
+        from,
+        0x80, // to
+        config.association_id()?,
+        req_id,
+        0x01,
+        0x17,
+        &SetZoneTemperatureMsg::new(zone, ambient, setpoint)?,
+    )?;
+
+    loop {
+        if let Some(payload) = filter(
+            &rf.recv_timeout(Duration::new(15, 0))?,
+            0x80,
+            from,
+            config.association_id()?,
+            req_id,
+        )? {
+            let (meta, data) = from_bytes(payload)?;
+            println!("RECV {} {}", meta, data);
+            return Ok((meta, data));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_temperature_message() {
+        let msg = SetZoneTemperatureMsg::new(2, Celsius::new(19.46), Celsius::new(20.5)).unwrap();
+        assert_eq!(
+            hex::encode(msg.to_bytes().unwrap()),
+            "9c540004a03400020400c300cd"
+        );
+
+        let payload = hex::decode("1380094a1001179c540004a03400020400c300cd").unwrap();
+        let (meta, data) = from_bytes(&payload).unwrap();
+        let x: SetZoneTemperatureMsg = data;
+        assert_eq!(meta.from_addr, 0x09);
+        assert_eq!(x, msg);
+    }
+
+    #[test]
+    fn test_zone_addr() {
+        assert_eq!(zone_addr(1), Ok(0x08));
+        assert_eq!(zone_addr(3), Ok(0x0a));
+        assert!(zone_addr(0).is_err());
+        assert!(zone_addr(4).is_err());
+    }
+}
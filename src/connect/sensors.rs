@@ -1,33 +1,39 @@
 use colored::Colorize;
 use deku::prelude::*;
 use hex;
+use serde::Serialize;
 use std::fmt;
 use std::time::Duration;
 
 use crate::config;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::serialize_hex;
+use crate::connect::temperature::Celsius;
 use crate::connect::{filter, from_bytes, send_cmd, Cmd, ConnectError, Metadata};
 use crate::rf::RFClient;
 
 use super::Assert;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 #[deku(endian = "big")]
 pub struct SensorsMsg {
     len: u8,
-    temp_ecs: i16,
-    temp_cdc: i16,
-    temp_depart_1: i16,
-    temp_depart_2: i16,
-    temp_depart_3: i16,
+    temp_ecs: Celsius,
+    temp_cdc: Celsius,
+    temp_depart_1: Celsius,
+    temp_depart_2: Celsius,
+    temp_depart_3: Celsius,
+    #[serde(serialize_with = "serialize_hex")]
     data: [u8; 26],
-    temp_ambi_1: i16,
-    temp_ambi_2: i16,
-    temp_ambi_3: i16,
+    temp_ambi_1: Celsius,
+    temp_ambi_2: Celsius,
+    temp_ambi_3: Celsius,
+    #[serde(serialize_with = "serialize_hex")]
     data2: [u8; 6],
-    temp_cons_1: i16,
-    temp_cons_2: i16,
-    temp_cons_3: i16,
-    temp_exterieur: i16,
+    temp_cons_1: Celsius,
+    temp_cons_2: Celsius,
+    temp_cons_3: Celsius,
+    temp_exterieur: Celsius,
 }
 
 impl fmt::Display for SensorsMsg {
@@ -121,12 +127,35 @@ impl fmt::Display for SensorsMsg {
     }
 }
 
+impl SensorsMsg {
+    pub fn temperatures(&self) -> [(&'static str, Celsius); 12] {
+        [
+            ("Temp ECS", self.temp_ecs),
+            ("Temp CDC", self.temp_cdc),
+            ("Temp Depart 1", self.temp_depart_1),
+            ("Temp Depart 2", self.temp_depart_2),
+            ("Temp Depart 3", self.temp_depart_3),
+            ("Temp Ambi 1", self.temp_ambi_1),
+            ("Temp Ambi 2", self.temp_ambi_2),
+            ("Temp Ambi 3", self.temp_ambi_3),
+            ("Temp Cons 1", self.temp_cons_1),
+            ("Temp Cons 2", self.temp_cons_2),
+            ("Temp Cons 3", self.temp_cons_3),
+            ("Temp Exterieur", self.temp_exterieur),
+        ]
+    }
+}
+
 impl Assert for SensorsMsg {
     fn assert(&self) -> bool {
         self.len as usize == 0x38 // length is expected to represent the msg length(56)
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<SensorsMsg>(Direction::Response, "79e0001c", None, None, "SensorsMsg");
+}
+
 pub fn connect_sensors(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Frisquet,
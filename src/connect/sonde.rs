@@ -1,17 +1,22 @@
 use crate::config;
+use crate::connect::decoder::{Direction, Registry};
+use crate::connect::temperature::Celsius;
 use crate::connect::{filter, from_bytes, send_cmd, Assert, ConnectError, Metadata};
+use crate::connect::{serialize_bcd, serialize_hex};
 use crate::rf::RFClient;
 use colored::Colorize;
 use deku::prelude::*;
+use serde::Serialize;
 use std::fmt::Error;
 use std::time::Duration;
 use std::{fmt, result};
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 pub struct SetExternalTemperatureMsg {
+    #[serde(serialize_with = "serialize_hex")]
     data: [u8; 9],
     #[deku(endian = "big")]
-    temperature: i16,
+    temperature: Celsius,
 }
 
 impl fmt::Display for SetExternalTemperatureMsg {
@@ -27,9 +32,10 @@ impl Assert for SetExternalTemperatureMsg {
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 pub struct ExternalTemperatureInitMsg {
     #[deku(count = "2")]
+    #[serde(serialize_with = "serialize_hex")]
     data: Vec<u8>,
 }
 
@@ -46,14 +52,20 @@ impl Assert for ExternalTemperatureInitMsg {
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 pub struct SetExternalTemperatureReplyMsg {
     len: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     year: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     month: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     day: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     hour: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     minute: u8,
+    #[serde(serialize_with = "serialize_bcd")]
     second: u8,
     data: u8,
     weekday: u8,
@@ -109,9 +121,10 @@ impl Assert for SetExternalTemperatureReplyMsg {
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, DekuRead, DekuWrite, Serialize)]
 pub struct ExternalTemperatureInitReply {
     #[deku(count = "0")]
+    #[serde(serialize_with = "serialize_hex")]
     pub data: Vec<u8>,
 }
 
@@ -128,6 +141,46 @@ impl Assert for ExternalTemperatureInitReply {
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register::<SetExternalTemperatureMsg>(
+        Direction::Request,
+        "9c540004",
+        Some(11),
+        None,
+        "SetExternalTemperatureMsg",
+    );
+    registry.register::<SetExternalTemperatureReplyMsg>(
+        Direction::Response,
+        "9c540004",
+        None,
+        None,
+        "SetExternalTemperatureReplyMsg",
+    );
+    registry.register::<ExternalTemperatureInitMsg>(
+        Direction::Request,
+        "43",
+        None,
+        None,
+        "ExternalTemperatureInitMsg",
+    );
+    registry.register::<ExternalTemperatureInitReply>(
+        Direction::Response,
+        "43",
+        None,
+        None,
+        "ExternalTemperatureInitReply",
+    );
+}
+
+/// Range reported by a real sonde, in °C.
+pub const MIN_TEMPERATURE: f32 = -40.0;
+pub const MAX_TEMPERATURE: f32 = 50.0;
+
+/// Temperature as sent by the sonde, clamped to the sonde range.
+pub fn encode_temperature(temperature: Celsius) -> Celsius {
+    temperature.clamp(Celsius::new(MIN_TEMPERATURE), Celsius::new(MAX_TEMPERATURE))
+}
+
 pub fn send_init(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Frisquet,
@@ -167,7 +220,7 @@ pub fn send_init(
 pub fn send_temperature(
     rf: &mut Box<dyn RFClient>,
     config: &mut config::Frisquet,
-    temperature: f32,
+    temperature: Celsius,
 ) -> Result<(Metadata, SetExternalTemperatureReplyMsg), ConnectError> {
     rf.set_network_id(Vec::from(config.network_id()?))?;
 
@@ -183,7 +236,7 @@ pub fn send_temperature(
         0x17,
         &SetExternalTemperatureMsg {
             data: [0x9c, 0x54, 0x00, 0x04, 0xa0, 0x29, 0x00, 0x01, 0x02],
-            temperature: (temperature * 10.0) as i16,
+            temperature: encode_temperature(temperature),
         },
     )?;
 
@@ -231,11 +284,21 @@ mod tests {
             x,
             SetExternalTemperatureMsg {
                 data: [156, 84, 0, 4, 160, 41, 0, 1, 2],
-                temperature: 92,
+                temperature: Celsius::from_tenths(92),
             }
         );
     }
 
+    #[test]
+    fn test_encode_temperature() {
+        assert_eq!(encode_temperature(Celsius::new(9.2)).tenths(), 92);
+        assert_eq!(encode_temperature(Celsius::new(-0.06)).tenths(), -1);
+        assert_eq!(encode_temperature(Celsius::new(-3.27)).tenths(), -33);
+        assert_eq!(encode_temperature(Celsius::new(-3.24)).tenths(), -32);
+        assert_eq!(encode_temperature(Celsius::new(-55.0)).tenths(), -400);
+        assert_eq!(encode_temperature(Celsius::new(80.0)).tenths(), 500);
+    }
+
     #[test]
     fn test_announce_response() {
         let payload = hex::decode("06802020948241").unwrap();
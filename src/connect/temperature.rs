@@ -0,0 +1,192 @@
+use deku::prelude::*;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, NumberValidation, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::connect::ConnectError;
+
+/// Temperature in 0.1°C, as sent by sensors, the sonde and satellites.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(endian = "endian", ctx = "endian: deku::ctx::Endian")]
+pub struct Celsius(i16);
+
+impl Celsius {
+    /// Rounded to 0.1°C.
+    pub fn new(celsius: f32) -> Celsius {
+        Celsius((celsius * 10.0).round() as i16)
+    }
+
+    pub fn from_tenths(tenths: i16) -> Celsius {
+        Celsius(tenths)
+    }
+
+    pub fn tenths(&self) -> i16 {
+        self.0
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.0 as f32 / 10.0
+    }
+}
+
+impl fmt::Display for Celsius {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}", self.to_f32())
+    }
+}
+
+impl FromStr for Celsius {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Celsius, String> {
+        s.parse::<f32>()
+            .map(Celsius::new)
+            .map_err(|_| format!("invalid temperature: {}", s))
+    }
+}
+
+impl Serialize for Celsius {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 10.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Celsius {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Celsius, D::Error> {
+        Ok(Celsius::new(f32::deserialize(deserializer)?))
+    }
+}
+
+impl JsonSchema for Celsius {
+    fn schema_name() -> String {
+        "Celsius".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Number.into()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Zone setpoint, 5.0 to 30.5°C by 0.5°C, sent as 0.1°C above 5°C.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(ctx = "_endian: deku::ctx::Endian")]
+pub struct Setpoint(u8);
+
+impl Setpoint {
+    pub const MIN: f32 = 5.0;
+    pub const MAX: f32 = 30.5;
+
+    pub fn new(celsius: f32) -> Result<Setpoint, ConnectError> {
+        let raw = (celsius * 10.0).round() as i32 - 50;
+        if raw < 0 {
+            Err(ConnectError::new(&format!(
+                "setpoint minimum is {:.1}",
+                Setpoint::MIN
+            )))
+        } else if raw > u8::MAX as i32 {
+            Err(ConnectError::new(&format!(
+                "setpoint maximum is {:.1}",
+                Setpoint::MAX
+            )))
+        } else if raw % 5 != 0 {
+            Err(ConnectError::new("setpoint should be 0.5 inc"))
+        } else {
+            Ok(Setpoint(raw as u8))
+        }
+    }
+
+    /// As sent, without step check, e.g. decoded from a frame.
+    pub fn from_raw(raw: u8) -> Setpoint {
+        Setpoint(raw)
+    }
+
+    pub fn raw(&self) -> u8 {
+        self.0
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        (self.0 as f32 + 50.0) / 10.0
+    }
+}
+
+impl fmt::Display for Setpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}", self.to_f32())
+    }
+}
+
+impl FromStr for Setpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Setpoint, String> {
+        let celsius: f32 = s.parse().map_err(|_| format!("invalid setpoint: {}", s))?;
+        Setpoint::new(celsius).map_err(|e| e.to_string())
+    }
+}
+
+impl Serialize for Setpoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64((self.0 as f64 + 50.0) / 10.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Setpoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Setpoint, D::Error> {
+        Setpoint::new(f32::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for Setpoint {
+    fn schema_name() -> String {
+        "Setpoint".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Number.into()),
+            number: Some(Box::new(NumberValidation {
+                minimum: Some(Setpoint::MIN as f64),
+                maximum: Some(Setpoint::MAX as f64),
+                multiple_of: Some(0.5),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setpoint() {
+        assert_eq!(Setpoint::new(21.5).unwrap().raw(), 165);
+        assert_eq!(Setpoint::new(5.0).unwrap().raw(), 0);
+        assert_eq!(Setpoint::new(30.5).unwrap().raw(), 255);
+        assert!(Setpoint::new(4.5).is_err());
+        assert!(Setpoint::new(31.0).is_err());
+        assert!(Setpoint::new(21.2).is_err());
+        assert_eq!(Setpoint::from_raw(165).to_string(), "21.5");
+        assert_eq!("19".parse::<Setpoint>().unwrap().raw(), 140);
+    }
+
+    #[test]
+    fn test_celsius() {
+        assert_eq!(Celsius::new(19.46).tenths(), 195);
+        assert_eq!(Celsius::new(-3.04).to_string(), "-3.0");
+        assert_eq!(
+            "12.4".parse::<Celsius>().unwrap(),
+            Celsius::from_tenths(124)
+        );
+        assert_eq!(serde_json::to_string(&Celsius::new(19.5)).unwrap(), "19.5");
+    }
+}
@@ -0,0 +1,221 @@
+use colored::Colorize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::connect::Metadata;
+
+/// Control values of requests expecting a response (control | 0x80).
+const REQUEST_CONTROLS: [u8; 3] = [0x01, 0x02, 0x08];
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// association id, request id, requester, responder
+type Key = (u8, u8, u8, u8);
+
+/// Counters of a device, as a requester and as a responder.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DeviceStats {
+    pub frames: u32,
+    /// requests sent by the device, retransmissions excluded
+    pub requests: u32,
+    pub retransmissions: u32,
+    /// requests received by the device and answered
+    pub answered: u32,
+    /// requests received by the device and never answered
+    pub unanswered: u32,
+    /// responses not matching any request
+    pub unmatched: u32,
+    pub latency_min: Option<Duration>,
+    pub latency_max: Option<Duration>,
+    latency_total: Duration,
+    pub last_seen: Option<Instant>,
+}
+
+impl DeviceStats {
+    pub fn latency_avg(&self) -> Option<Duration> {
+        match self.answered {
+            0 => None,
+            n => Some(self.latency_total / n),
+        }
+    }
+
+    fn add_latency(&mut self, latency: Duration) {
+        self.answered += 1;
+        self.latency_total += latency;
+        self.latency_min = Some(self.latency_min.map_or(latency, |l| l.min(latency)));
+        self.latency_max = Some(self.latency_max.map_or(latency, |l| l.max(latency)));
+    }
+}
+
+/// Passive conversation tracker: pairs requests with their responses by
+/// addresses, association id and request id.
+pub struct Tracker {
+    timeout: Duration,
+    /// last transmission of requests waiting for a response
+    pending: HashMap<Key, Instant>,
+    devices: BTreeMap<u8, DeviceStats>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Tracker::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Tracker {
+    /// A request without response after `timeout` is unanswered.
+    pub fn new(timeout: Duration) -> Tracker {
+        Tracker {
+            timeout,
+            pending: HashMap::new(),
+            devices: BTreeMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, meta: &Metadata, now: Instant) {
+        self.expire(now);
+
+        let from = self.devices.entry(meta.from_addr).or_default();
+        from.frames += 1;
+        from.last_seen = Some(now);
+
+        if REQUEST_CONTROLS.contains(&meta.control) {
+            let key = (
+                meta.association_id,
+                meta.request_id,
+                meta.from_addr,
+                meta.to_addr,
+            );
+            match self.pending.insert(key, now) {
+                Some(_) => from.retransmissions += 1,
+                None => from.requests += 1,
+            }
+        } else if REQUEST_CONTROLS.contains(&(meta.control & 0x7f)) && meta.control & 0x80 != 0 {
+            let key = (
+                meta.association_id,
+                meta.request_id,
+                meta.to_addr,
+                meta.from_addr,
+            );
+            // broadcast requests are answered by any device
+            let broadcast = (meta.association_id, meta.request_id, meta.to_addr, 0x00);
+            match self
+                .pending
+                .remove(&key)
+                .or_else(|| self.pending.remove(&broadcast))
+            {
+                Some(sent) => from.add_latency(now.duration_since(sent)),
+                None => from.unmatched += 1,
+            }
+        }
+    }
+
+    /// Count requests without response for longer than the timeout as
+    /// unanswered by their recipient.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let expired: Vec<Key> = self
+            .pending
+            .iter()
+            .filter(|(_, sent)| now.duration_since(**sent) > timeout)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired {
+            self.pending.remove(&key);
+            self.devices.entry(key.3).or_default().unanswered += 1;
+        }
+    }
+
+    /// Requests waiting for a response.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn devices(&self) -> &BTreeMap<u8, DeviceStats> {
+        &self.devices
+    }
+}
+
+fn fmt_latency(latency: Option<Duration>) -> String {
+    latency.map_or("-".into(), |l| format!("{}ms", l.as_millis()))
+}
+
+impl fmt::Display for Tracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            "addr  frames  req  retx  answered  unanswered  unmatched  latency min/avg/max".cyan()
+        )?;
+        for (addr, s) in &self.devices {
+            let line = format!(
+                "\n0x{:02x}  {:6}  {:3}  {:4}  {:8}  {:10}  {:9}  {}/{}/{}",
+                addr,
+                s.frames,
+                s.requests,
+                s.retransmissions,
+                s.answered,
+                s.unanswered,
+                s.unmatched,
+                fmt_latency(s.latency_min),
+                fmt_latency(s.latency_avg()),
+                fmt_latency(s.latency_max),
+            );
+            if s.unanswered > 0 {
+                write!(f, "{}", line.red())?;
+            } else {
+                write!(f, "{}", line)?;
+            }
+        }
+        write!(f, "\npending: {}", self.pending.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deku::prelude::*;
+
+    fn meta(frame: &str) -> Metadata {
+        Metadata::from_bytes((&hex::decode(frame).unwrap(), 0))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_latency_and_retransmission() {
+        let start = Instant::now();
+        let mut tracker = Tracker::new(Duration::from_secs(15));
+
+        // connect reads sensors, retransmits, then the boiler answers
+        let request = meta("0a807e12d4010379e0001c");
+        tracker.push(&request, start);
+        tracker.push(&request, start + Duration::from_secs(2));
+        tracker.push(&meta("3f7e8012d48103"), start + Duration::from_millis(2300));
+
+        let connect = &tracker.devices()[&0x7e];
+        assert_eq!(connect.requests, 1);
+        assert_eq!(connect.retransmissions, 1);
+        let boiler = &tracker.devices()[&0x80];
+        assert_eq!(boiler.answered, 1);
+        assert_eq!(boiler.latency_min, Some(Duration::from_millis(300)));
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn test_unanswered_and_unmatched() {
+        let start = Instant::now();
+        let mut tracker = Tracker::new(Duration::from_secs(15));
+
+        tracker.push(&meta("118020ba4001179c540004"), start);
+        tracker.expire(start + Duration::from_secs(16));
+        assert_eq!(tracker.devices()[&0x80].unanswered, 1);
+        assert_eq!(tracker.pending(), 0);
+
+        // late response
+        tracker.push(&meta("0f2080ba408117"), start + Duration::from_secs(17));
+        assert_eq!(tracker.devices()[&0x80].unmatched, 1);
+        assert_eq!(tracker.devices()[&0x80].answered, 0);
+    }
+}
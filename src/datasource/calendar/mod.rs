@@ -0,0 +1,639 @@
+use chrono::{
+    Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use ical::parser::ical::component::IcalEvent;
+use ical::IcalParser;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::time::{Duration, Instant};
+use std::{error, fmt};
+
+use crate::config::CalendarConfig;
+
+const DEFAULT_TAG: &str = "heating";
+const DEFAULT_REFRESH: u64 = 15 * 60;
+/// Days ahead recurring events are expanded to.
+const EXPAND_DAYS: i64 = 60;
+/// Bound on the periods walked through when expanding an event.
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EventKind {
+    Comfort,
+    Reduced,
+    Holiday,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Comfort => write!(f, "comfort"),
+            EventKind::Reduced => write!(f, "reduced"),
+            EventKind::Holiday => write!(f, "holiday"),
+        }
+    }
+}
+
+/// Heating event, local times, end excluded.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event {
+    pub kind: EventKind,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub summary: String,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} - {} {}",
+            self.kind,
+            self.start.format("%Y-%m-%d %H:%M"),
+            self.end.format("%Y-%m-%d %H:%M"),
+            self.summary
+        )
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct CalendarErr {
+    msg: String,
+}
+
+impl fmt::Display for CalendarErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.msg.fmt(f)
+    }
+}
+
+impl error::Error for CalendarErr {}
+
+impl From<String> for CalendarErr {
+    fn from(err: String) -> CalendarErr {
+        CalendarErr { msg: err }
+    }
+}
+
+/// Events of an `.ics` file or URL, reloaded every `refresh` seconds.
+pub struct Calendar {
+    source: String,
+    tag: String,
+    refresh: Duration,
+    /// last loaded events, `None` until a load succeeds
+    events: Option<Vec<Event>>,
+    loaded: Option<Instant>,
+}
+
+impl Calendar {
+    pub fn new(config: &CalendarConfig) -> Calendar {
+        Calendar {
+            source: config.source.clone(),
+            tag: config.tag.clone().unwrap_or(DEFAULT_TAG.into()),
+            refresh: Duration::from_secs(config.refresh.unwrap_or(DEFAULT_REFRESH)),
+            events: None,
+            loaded: None,
+        }
+    }
+
+    /// Heating events, the last loaded ones if the source fails. An error
+    /// only when it never loaded.
+    pub fn events(&mut self, now: Instant) -> Result<&[Event], CalendarErr> {
+        if self
+            .loaded
+            .is_none_or(|at| now.duration_since(at) >= self.refresh)
+        {
+            // retry at the next refresh, even on failure
+            self.loaded = Some(now);
+            let today = Local::now().naive_local();
+            let from = today - chrono::Duration::days(1);
+            let to = today + chrono::Duration::days(EXPAND_DAYS);
+            match load(&self.source).and_then(|ics| parse(&ics, &self.tag, from, to)) {
+                Ok(events) => self.events = Some(events),
+                Err(e) if self.events.is_some() => {
+                    println!("Calendar not refreshed, keeping the last events: {}", e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(self.events.as_deref().unwrap_or_default())
+    }
+}
+
+/// Content of a local file or an http(s) URL.
+pub fn load(source: &str) -> Result<String, CalendarErr> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        reqwest::blocking::get(source)
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| CalendarErr::from(format!("{}: {}", source, e)))
+    } else {
+        read_to_string(source).map_err(|e| CalendarErr::from(format!("{}: {}", source, e)))
+    }
+}
+
+/// Events tagged `<tag>:comfort`, `<tag>:reduced` or `<tag>:holiday` in
+/// their categories or summary, invalid ones are logged and skipped.
+/// Recurring events are expanded to their occurrences ending after `from`
+/// and starting before `to`.
+pub fn parse(
+    ics: &str,
+    tag: &str,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<Vec<Event>, CalendarErr> {
+    let mut events = vec![];
+    for calendar in IcalParser::new(ics.as_bytes()) {
+        let calendar = calendar.map_err(|e| CalendarErr::from(format!("invalid ics: {}", e)))?;
+
+        // occurrences moved or changed by another event with the same UID
+        let mut overridden: HashMap<String, Vec<NaiveDateTime>> = HashMap::new();
+        for event in &calendar.events {
+            if let (Some(uid), Some(id)) = (value(event, "UID"), value(event, "RECURRENCE-ID")) {
+                if let Ok((id, _)) = parse_date(&id) {
+                    overridden.entry(uid).or_default().push(id);
+                }
+            }
+        }
+
+        for event in &calendar.events {
+            let uid = value(event, "UID").unwrap_or_default();
+            let excluded = overridden.get(&uid).map_or(&[][..], Vec::as_slice);
+            match to_events(event, tag, excluded, from, to) {
+                Ok(occurrences) => events.extend(occurrences),
+                Err(e) => println!("Calendar event skipped: {}", e),
+            }
+        }
+    }
+    events.sort_by_key(|e| e.start);
+    Ok(events)
+}
+
+fn value(event: &IcalEvent, name: &str) -> Option<String> {
+    event
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .and_then(|p| p.value.clone())
+}
+
+/// The event, or its occurrences when recurring, none when not tagged.
+fn to_events(
+    event: &IcalEvent,
+    tag: &str,
+    excluded: &[NaiveDateTime],
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<Vec<Event>, CalendarErr> {
+    let summary = value(event, "SUMMARY").unwrap_or_default();
+    let tags = format!(
+        "{} {}",
+        value(event, "CATEGORIES").unwrap_or_default(),
+        summary
+    )
+    .to_lowercase();
+    let tag = tag.to_lowercase();
+    let kind = [EventKind::Comfort, EventKind::Reduced, EventKind::Holiday]
+        .into_iter()
+        .find(|kind| tags.contains(&format!("{}:{}", tag, kind)));
+    let Some(kind) = kind else {
+        return Ok(vec![]);
+    };
+
+    let start = value(event, "DTSTART")
+        .ok_or_else(|| CalendarErr::from(format!("{}: missing DTSTART", summary)))?;
+    let (start, all_day) = parse_date(&start)?;
+    let end = match value(event, "DTEND") {
+        Some(end) => parse_date(&end)?.0,
+        // all day events without end last one day
+        None if all_day => start + chrono::Duration::days(1),
+        None => return Err(CalendarErr::from(format!("{}: missing DTEND", summary))),
+    };
+    if end <= start {
+        return Err(CalendarErr::from(format!(
+            "{}: ends before it starts",
+            summary
+        )));
+    }
+
+    let Some(rule) = value(event, "RRULE") else {
+        return Ok(vec![Event {
+            kind,
+            start,
+            end,
+            summary,
+        }]);
+    };
+    let mut excluded = excluded.to_vec();
+    for property in event.properties.iter().filter(|p| p.name == "EXDATE") {
+        for date in property.value.iter().flat_map(|v| v.split(',')) {
+            excluded.push(parse_date(date)?.0);
+        }
+    }
+    let duration = end - start;
+    let occurrences = Rule::parse(&rule)
+        .and_then(|rule| rule.occurrences(start, to))
+        .map_err(|e| CalendarErr::from(format!("{}: {}", summary, e)))?;
+    Ok(occurrences
+        .into_iter()
+        .filter(|start| !excluded.contains(start) && *start + duration > from)
+        .map(|start| Event {
+            kind,
+            start,
+            end: start + duration,
+            summary: summary.clone(),
+        })
+        .collect())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Supported subset of an RRULE: the frequency with its interval, count,
+/// end and week days.
+#[derive(Debug, PartialEq)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<NaiveDateTime>,
+    days: Vec<Weekday>,
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Rule, CalendarErr> {
+        let unsupported = |part: &str| CalendarErr::from(format!("unsupported RRULE {}", part));
+        let mut frequency = None;
+        let mut rule_ = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            days: vec![],
+        };
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| unsupported(part))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(unsupported(part)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule_.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| unsupported(part))?
+                }
+                "COUNT" => rule_.count = Some(value.parse().map_err(|_| unsupported(part))?),
+                "UNTIL" => rule_.until = Some(parse_date(value)?.0),
+                "BYDAY" => {
+                    rule_.days = value
+                        .split(',')
+                        .map(|day| weekday(day).ok_or_else(|| unsupported(part)))
+                        .collect::<Result<_, _>>()?
+                }
+                // weeks start on monday, the default
+                "WKST" if value == "MO" => {}
+                _ => return Err(unsupported(part)),
+            }
+        }
+        rule_.frequency = frequency.ok_or_else(|| unsupported("without FREQ"))?;
+        if !rule_.days.is_empty()
+            && !matches!(rule_.frequency, Frequency::Daily | Frequency::Weekly)
+        {
+            return Err(unsupported("BYDAY with a monthly or yearly FREQ"));
+        }
+        Ok(rule_)
+    }
+
+    /// Starts of the occurrences before `to`, from the first one at `start`.
+    fn occurrences(
+        &self,
+        start: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<NaiveDateTime>, CalendarErr> {
+        let mut starts = vec![];
+        for period in 0..MAX_PERIODS {
+            let Some((begin, dates)) = self.period(start.date(), period * self.interval) else {
+                break;
+            };
+            let begin = begin.and_time(NaiveTime::MIN);
+            if begin >= to || self.until.is_some_and(|until| begin > until) {
+                return Ok(starts);
+            }
+            for date in dates {
+                let candidate = date.and_time(start.time());
+                if candidate < start {
+                    continue;
+                }
+                if candidate >= to
+                    || self.until.is_some_and(|until| candidate > until)
+                    || self.count.is_some_and(|count| starts.len() >= count)
+                {
+                    return Ok(starts);
+                }
+                starts.push(candidate);
+            }
+        }
+        Err(CalendarErr::from("too many occurrences".to_string()))
+    }
+
+    /// First day of the `n`th period after the one of `start` with the
+    /// occurrence days in it.
+    fn period(&self, start: NaiveDate, n: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let days = |from: NaiveDate, n: u32| from.checked_add_days(Days::new(n.into()));
+        match self.frequency {
+            Frequency::Daily => {
+                let date = days(start, n)?;
+                let matches = self.days.is_empty() || self.days.contains(&date.weekday());
+                Some((date, if matches { vec![date] } else { vec![] }))
+            }
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday().into()))?;
+                let monday = days(monday, n.checked_mul(7)?)?;
+                let mut dates: Vec<_> = if self.days.is_empty() {
+                    vec![days(start, n.checked_mul(7)?)?]
+                } else {
+                    self.days
+                        .iter()
+                        .filter_map(|day| days(monday, day.num_days_from_monday()))
+                        .collect()
+                };
+                dates.sort();
+                Some((monday, dates))
+            }
+            Frequency::Monthly => {
+                let first = start.with_day(1)?.checked_add_months(Months::new(n))?;
+                // months without that day are skipped
+                let date = first.with_day(start.day());
+                Some((first, date.into_iter().collect()))
+            }
+            Frequency::Yearly => {
+                let first =
+                    NaiveDate::from_ymd_opt(start.year().checked_add(n.try_into().ok()?)?, 1, 1)?;
+                let date = NaiveDate::from_ymd_opt(first.year(), start.month(), start.day());
+                Some((first, date.into_iter().collect()))
+            }
+        }
+    }
+}
+
+fn weekday(day: &str) -> Option<Weekday> {
+    Some(match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// Local time of an iCalendar date or date time, and whether it is a date.
+/// Times with a TZID are taken as local times.
+fn parse_date(value: &str) -> Result<(NaiveDateTime, bool), CalendarErr> {
+    let invalid = || CalendarErr::from(format!("invalid date: {}", value));
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok((date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok((
+            Utc.from_utc_datetime(&utc)
+                .with_timezone(&Local)
+                .naive_local(),
+            false,
+        ));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map(|date| (date, false))
+        .map_err(|_| invalid())
+}
+
+/// Comfort or reduced event in progress, the latest started one.
+pub fn active_override(events: &[Event], now: NaiveDateTime) -> Option<&Event> {
+    events
+        .iter()
+        .filter(|e| e.kind != EventKind::Holiday && e.start <= now && now < e.end)
+        .max_by_key(|e| e.start)
+}
+
+/// Holiday in progress or the next one.
+pub fn next_holiday(events: &[Event], now: NaiveDateTime) -> Option<&Event> {
+    events
+        .iter()
+        .filter(|e| e.kind == EventKind::Holiday && now < e.end)
+        .min_by_key(|e| e.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+SUMMARY:Guests heating:comfort\r
+DTSTART:20241012T180000\r
+DTEND:20241012T230000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Office closed\r
+CATEGORIES:Work,HEATING:REDUCED\r
+DTSTART:20241012T200000\r
+DTEND:20241013T080000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Skiing\r
+CATEGORIES:heating:holiday\r
+DTSTART;VALUE=DATE:20241220\r
+DTEND;VALUE=DATE:20241227\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Dentist\r
+DTSTART:20241012T100000\r
+DTEND:20241012T110000\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let events = parse(
+            ICS,
+            "heating",
+            at("2024-10-01 00:00"),
+            at("2025-01-01 00:00"),
+        )
+        .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].kind, EventKind::Comfort);
+        assert_eq!(events[0].start, at("2024-10-12 18:00"));
+        assert_eq!(events[1].kind, EventKind::Reduced);
+        assert_eq!(events[2].kind, EventKind::Holiday);
+        assert_eq!(events[2].end, at("2024-12-27 00:00"));
+    }
+
+    #[test]
+    fn test_active() {
+        let events = parse(
+            ICS,
+            "heating",
+            at("2024-10-01 00:00"),
+            at("2025-01-01 00:00"),
+        )
+        .unwrap();
+
+        assert_eq!(active_override(&events, at("2024-10-12 17:00")), None);
+        let active = active_override(&events, at("2024-10-12 19:00")).unwrap();
+        assert_eq!(active.kind, EventKind::Comfort);
+        // the latest started wins
+        let active = active_override(&events, at("2024-10-12 21:00")).unwrap();
+        assert_eq!(active.kind, EventKind::Reduced);
+
+        let holiday = next_holiday(&events, at("2024-10-12 21:00")).unwrap();
+        assert_eq!(holiday.summary, "Skiing");
+        assert_eq!(next_holiday(&events, at("2024-12-27 00:00")), None);
+    }
+
+    #[test]
+    fn test_invalid_event() {
+        // skipped, the others are kept
+        let ics = ICS.replace("DTEND:20241012T230000", "DTEND:20241012T170000");
+        let events = parse(
+            &ics,
+            "heating",
+            at("2024-10-01 00:00"),
+            at("2025-01-01 00:00"),
+        )
+        .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::Reduced);
+    }
+
+    #[test]
+    fn test_recurring() {
+        let ics = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:office\r
+SUMMARY:Office heating:comfort\r
+DTSTART:20241007T080000\r
+DTEND:20241007T120000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20241031T235959\r
+EXDATE:20241014T080000,20241016T080000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:office\r
+RECURRENCE-ID:20241021T080000\r
+SUMMARY:Office heating:comfort\r
+DTSTART:20241021T100000\r
+DTEND:20241021T120000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Gym heating:reduced\r
+DTSTART:20240131T180000\r
+DTEND:20240131T200000\r
+RRULE:FREQ=MONTHLY;COUNT=4\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Club heating:comfort\r
+DTSTART:20241001T180000\r
+DTEND:20241001T200000\r
+RRULE:FREQ=MONTHLY;BYDAY=1TU\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let events = parse(
+            ics,
+            "heating",
+            at("2024-10-09 00:00"),
+            at("2024-10-28 00:00"),
+        )
+        .unwrap();
+        let starts: Vec<_> = events.iter().map(|e| (e.kind, e.start, e.end)).collect();
+        assert_eq!(
+            starts,
+            vec![
+                (
+                    EventKind::Comfort,
+                    at("2024-10-09 08:00"),
+                    at("2024-10-09 12:00")
+                ),
+                // moved occurrence
+                (
+                    EventKind::Comfort,
+                    at("2024-10-21 10:00"),
+                    at("2024-10-21 12:00")
+                ),
+                (
+                    EventKind::Comfort,
+                    at("2024-10-23 08:00"),
+                    at("2024-10-23 12:00")
+                ),
+            ]
+        );
+
+        // months without a 31st are skipped, four occurrences in all
+        let events = parse(
+            ics,
+            "heating",
+            at("2024-01-01 00:00"),
+            at("2025-01-01 00:00"),
+        )
+        .unwrap();
+        let gym: Vec<_> = events
+            .iter()
+            .filter(|e| e.kind == EventKind::Reduced)
+            .map(|e| e.start)
+            .collect();
+        assert_eq!(
+            gym,
+            vec![
+                at("2024-01-31 18:00"),
+                at("2024-03-31 18:00"),
+                at("2024-05-31 18:00"),
+                at("2024-07-31 18:00"),
+            ]
+        );
+        // unsupported rule skipped
+        assert!(!events.iter().any(|e| e.summary.starts_with("Club")));
+    }
+
+    #[test]
+    fn test_failed_refresh() {
+        let path = std::env::temp_dir().join(format!("frisquet-{}.ics", std::process::id()));
+        let mut calendar = Calendar::new(&CalendarConfig {
+            source: path.to_string_lossy().into(),
+            tag: None,
+            refresh: Some(60),
+            write_holidays: None,
+        });
+        let now = Instant::now();
+        assert!(calendar.events(now).is_err());
+
+        std::fs::write(&path, ICS).unwrap();
+        let later = now + Duration::from_secs(60);
+        assert_eq!(calendar.events(later).unwrap().len(), 3);
+        std::fs::remove_file(&path).unwrap();
+        let events = calendar.events(later + Duration::from_secs(60)).unwrap();
+        assert_eq!(events.len(), 3);
+    }
+}
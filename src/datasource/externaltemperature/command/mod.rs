@@ -0,0 +1,100 @@
+use std::io::Read;
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::datasource::externaltemperature::{
+    parse_temperature, scale, ExternalTemperatureErr, ExternalTemperatureSource,
+};
+
+const DEFAULT_TIMEOUT: u64 = 5;
+const POLL: Duration = Duration::from_millis(50);
+
+/// Number printed by a shell command.
+pub struct Command {
+    command: String,
+    scale: Option<f32>,
+    offset: Option<f32>,
+    timeout: Duration,
+}
+
+impl Command {
+    pub fn new(config: &config::CommandSource) -> Command {
+        Command {
+            command: config.command.clone(),
+            scale: config.scale,
+            offset: config.offset,
+            timeout: Duration::from_secs(config.timeout.unwrap_or(DEFAULT_TIMEOUT)),
+        }
+    }
+}
+
+impl ExternalTemperatureSource for Command {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        let error = |e: String| ExternalTemperatureErr::from(format!("{}: {}", self.command, e));
+        let mut child = process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| error(e.to_string()))?;
+
+        // read while waiting, a full pipe would block the command
+        let mut stdout = child.stdout.take();
+        let reader = thread::spawn(move || {
+            let mut output = vec![];
+            if let Some(stdout) = &mut stdout {
+                let _ = stdout.read_to_end(&mut output);
+            }
+            output
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait().map_err(|e| error(e.to_string()))? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(error(format!("killed after {}s", self.timeout.as_secs())));
+                }
+                None => thread::sleep(POLL),
+            }
+        };
+        if !status.success() {
+            return Err(error(status.to_string()));
+        }
+        let output = reader
+            .join()
+            .map_err(|_| error("cannot read the output".into()))?;
+        let value = parse_temperature(&String::from_utf8_lossy(&output))?;
+        Ok(scale(value, self.scale, self.offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(command: &str) -> Command {
+        Command::new(&config::CommandSource {
+            command: command.into(),
+            scale: None,
+            offset: Some(-1.0),
+            timeout: Some(1),
+        })
+    }
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command("echo 7.25").get(), Ok(6.25));
+        assert!(command("echo 7.25; exit 1").get().is_err());
+        assert!(command("true").get().is_err());
+
+        let start = Instant::now();
+        assert!(command("sleep 10").get().is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
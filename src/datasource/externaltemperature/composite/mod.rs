@@ -0,0 +1,244 @@
+use std::time::{Duration, Instant};
+
+use crate::config::ConfigError;
+use crate::datasource::externaltemperature::{ExternalTemperatureErr, ExternalTemperatureSource};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Combine {
+    /// first source with a value, in priority order
+    First,
+    Median,
+    Mean,
+}
+
+impl Combine {
+    pub fn from_name(name: &str) -> Result<Combine, ConfigError> {
+        match name {
+            "first" => Ok(Combine::First),
+            "median" => Ok(Combine::Median),
+            "mean" => Ok(Combine::Mean),
+            x => Err(ConfigError::new(&format!(
+                "combine: invalid {}, expected first, median or mean",
+                x
+            ))),
+        }
+    }
+}
+
+/// A source of a composite, its values are used until they are older than
+/// `max_age`, also after it fails. Without `max_age`, any value it returns is
+/// used and none once it fails.
+pub struct Member {
+    pub name: String,
+    pub source: Box<dyn ExternalTemperatureSource>,
+    pub max_age: Option<Duration>,
+    last: Option<(f32, Instant)>,
+}
+
+impl Member {
+    pub fn new(
+        name: &str,
+        source: Box<dyn ExternalTemperatureSource>,
+        max_age: Option<Duration>,
+    ) -> Member {
+        Member {
+            name: name.into(),
+            source,
+            max_age,
+            last: None,
+        }
+    }
+
+    /// Read the source with the time of its value, `None` when it fails and
+    /// its last value is too old.
+    fn read(&mut self, now: Instant) -> Option<(f32, Instant)> {
+        match self.source.get() {
+            Ok(value) if value.is_finite() => {
+                let at = now.checked_sub(self.source.age()).unwrap_or(now);
+                match self.max_age {
+                    Some(max_age) if now.duration_since(at) > max_age => println!(
+                        "{} unavailable: value is {}s old",
+                        self.name,
+                        now.duration_since(at).as_secs()
+                    ),
+                    _ => {
+                        self.last = Some((value, at));
+                        return self.last;
+                    }
+                }
+            }
+            Ok(value) => println!("{} unavailable: invalid value {}", self.name, value),
+            Err(e) => println!("{} unavailable: {}", self.name, e),
+        }
+        let max_age = self.max_age.unwrap_or(Duration::ZERO);
+        match self.last {
+            Some((value, at)) if now.duration_since(at) <= max_age => Some((value, at)),
+            _ => None,
+        }
+    }
+}
+
+/// Sources tried in priority order, or combined.
+pub struct Composite {
+    members: Vec<Member>,
+    combine: Combine,
+    /// age of the oldest value in the last result
+    age: Duration,
+}
+
+impl Composite {
+    pub fn new(members: Vec<Member>, combine: Combine) -> Composite {
+        Composite {
+            members,
+            combine,
+            age: Duration::ZERO,
+        }
+    }
+
+    fn get_at(&mut self, now: Instant) -> Result<f32, ExternalTemperatureErr> {
+        let mut values = vec![];
+        let mut oldest = now;
+        for member in &mut self.members {
+            if let Some((value, at)) = member.read(now) {
+                values.push(value);
+                oldest = oldest.min(at);
+                if self.combine == Combine::First {
+                    break;
+                }
+            }
+        }
+        if values.is_empty() {
+            let names: Vec<&str> = self.members.iter().map(|m| m.name.as_str()).collect();
+            return Err(ExternalTemperatureErr::from(format!(
+                "no value from {}",
+                names.join(", ")
+            )));
+        }
+        self.age = now.duration_since(oldest);
+        Ok(match self.combine {
+            Combine::First => values[0],
+            Combine::Mean => values.iter().sum::<f32>() / values.len() as f32,
+            Combine::Median => {
+                values.sort_by(f32::total_cmp);
+                let mid = values.len() / 2;
+                match values.len() % 2 {
+                    0 => (values[mid - 1] + values[mid]) / 2.0,
+                    _ => values[mid],
+                }
+            }
+        })
+    }
+}
+
+impl ExternalTemperatureSource for Composite {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        self.get_at(Instant::now())
+    }
+
+    fn age(&self) -> Duration {
+        self.age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    /// Returns queued readings, fails when empty.
+    struct Queue(Rc<RefCell<VecDeque<f32>>>);
+
+    impl ExternalTemperatureSource for Queue {
+        fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+            self.0
+                .borrow_mut()
+                .pop_front()
+                .ok_or_else(|| ExternalTemperatureErr::from("down".to_string()))
+        }
+    }
+
+    fn member(name: &str, values: &[f32], max_age: u64) -> (Member, Rc<RefCell<VecDeque<f32>>>) {
+        let queue = Rc::new(RefCell::new(values.iter().copied().collect()));
+        let source = Box::new(Queue(queue.clone()));
+        (
+            Member::new(name, source, Some(Duration::from_secs(max_age))),
+            queue,
+        )
+    }
+
+    /// Always returns `value`, cached for `age`.
+    struct Cached {
+        value: f32,
+        age: Duration,
+    }
+
+    impl ExternalTemperatureSource for Cached {
+        fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+            Ok(self.value)
+        }
+
+        fn age(&self) -> Duration {
+            self.age
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let (mqtt, _) = member("mqtt", &[5.0], 300);
+        let (ha, _) = member("homeassistant", &[7.0, 8.0, 9.0], 0);
+        let mut composite = Composite::new(vec![mqtt, ha], Combine::First);
+        let now = Instant::now();
+
+        assert_eq!(composite.get_at(now), Ok(5.0));
+        assert_eq!(composite.age(), Duration::ZERO);
+        // mqtt down, its last value is still recent
+        assert_eq!(composite.get_at(now + Duration::from_secs(300)), Ok(5.0));
+        assert_eq!(composite.age(), Duration::from_secs(300));
+        assert_eq!(composite.get_at(now + Duration::from_secs(301)), Ok(7.0));
+        assert_eq!(composite.age(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let (a, _) = member("a", &[4.0, 4.0], 0);
+        let (b, b_values) = member("b", &[6.0], 0);
+        let (c, _) = member("c", &[20.0, 20.0], 0);
+        let mut composite = Composite::new(vec![a, b, c], Combine::Median);
+        let now = Instant::now();
+        let later = |secs| now + Duration::from_secs(secs);
+        assert_eq!(composite.get_at(now), Ok(6.0));
+        // b down
+        assert_eq!(composite.get_at(later(1)), Ok(12.0));
+        assert!(composite.get_at(later(2)).is_err());
+
+        b_values.borrow_mut().push_back(1.0);
+        composite.combine = Combine::Mean;
+        assert_eq!(composite.get_at(later(3)), Ok(1.0));
+    }
+
+    #[test]
+    fn test_stale_value() {
+        let cached = |max_age| {
+            let source = Box::new(Cached {
+                value: 5.0,
+                age: Duration::from_secs(3600),
+            });
+            Member::new("mqtt", source, max_age)
+        };
+        let (ha, _) = member("homeassistant", &[7.0, 8.0], 0);
+        let mut composite = Composite::new(
+            vec![cached(Some(Duration::from_secs(900))), ha],
+            Combine::First,
+        );
+        // late enough to date the cached value
+        let now = Instant::now() + Duration::from_secs(3600);
+        assert_eq!(composite.get_at(now), Ok(7.0));
+
+        // no max_age, the source decides how old its values may be
+        let mut composite = Composite::new(vec![cached(None)], Combine::First);
+        assert_eq!(composite.get_at(now), Ok(5.0));
+        assert_eq!(composite.age(), Duration::from_secs(3600));
+    }
+}
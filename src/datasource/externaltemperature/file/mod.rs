@@ -0,0 +1,54 @@
+use std::fs::read_to_string;
+
+use crate::config;
+use crate::datasource::externaltemperature::{
+    parse_temperature, scale, ExternalTemperatureErr, ExternalTemperatureSource,
+};
+
+/// Number read from a file, written by another program.
+pub struct File {
+    path: String,
+    scale: Option<f32>,
+    offset: Option<f32>,
+}
+
+impl File {
+    pub fn new(config: &config::FileSource) -> File {
+        File {
+            path: config.path.clone(),
+            scale: config.scale,
+            offset: config.offset,
+        }
+    }
+}
+
+impl ExternalTemperatureSource for File {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        let content = read_to_string(&self.path)
+            .map_err(|e| ExternalTemperatureErr::from(format!("{}: {}", self.path, e)))?;
+        Ok(scale(parse_temperature(&content)?, self.scale, self.offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{remove_file, write};
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("frisquet-outdoor-{}", std::process::id()));
+        let mut source = File::new(&config::FileSource {
+            path: path.to_string_lossy().into(),
+            scale: Some(0.1),
+            offset: None,
+        });
+        assert!(source.get().is_err());
+
+        write(&path, "125\n").unwrap();
+        assert_eq!(source.get(), Ok(12.5));
+        write(&path, "n/a").unwrap();
+        assert!(source.get().is_err());
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use crate::config;
+use crate::datasource::externaltemperature::{ExternalTemperatureErr, ExternalTemperatureSource};
+
+/// Configured value, for tests or a season without a sensor.
+pub struct Fixed {
+    value: f32,
+}
+
+impl Fixed {
+    pub fn new(config: &config::StaticSource) -> Fixed {
+        Fixed {
+            value: config.value,
+        }
+    }
+}
+
+impl ExternalTemperatureSource for Fixed {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        Ok(self.value)
+    }
+}
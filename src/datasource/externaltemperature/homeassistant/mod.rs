@@ -1,7 +1,7 @@
-use crate::config;
-use crate::datasource::externaltemperature::ExternalTemperatureErr;
+use crate::config::{self, ConfigError};
+use crate::datasource::externaltemperature::{ExternalTemperatureErr, ExternalTemperatureSource};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::time::Duration;
 
 impl From<reqwest::Error> for ExternalTemperatureErr {
     fn from(value: reqwest::Error) -> Self {
@@ -9,35 +9,144 @@ impl From<reqwest::Error> for ExternalTemperatureErr {
     }
 }
 
-pub fn get_ha_client(config: &mut config::HAConfig) -> Result<f32, ExternalTemperatureErr> {
-    let ha_state_url = format!("{}/api/states/{}", config.host.as_str(), config.entity_id);
-
-    // request::blocking => Client.get().header(xxx, config.token)
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(ha_state_url)
-        .header("Authorization", format!("Bearer {}", config.token))
-        .send()?;
-    // println!("response: {}", response.text());
-    let response_json = response.json::<HashMap<String, Value>>()?;
-
-    let attribute = response_json
-        .get("attributes")
-        .ok_or(ExternalTemperatureErr::from(format!(
-            "Unknown field {}",
-            config.temperature_field
-        )))?;
-
-    let temp_str =
-        attribute
-            .get(config.temperature_field.as_str())
-            .ok_or(ExternalTemperatureErr::from(format!(
+/// State or attribute of a Home Assistant entity, read through the REST API.
+pub struct HomeAssistant {
+    config: config::HAConfig,
+    client: reqwest::blocking::Client,
+}
+
+impl HomeAssistant {
+    pub fn new(config: &config::HAConfig) -> Result<HomeAssistant, ConfigError> {
+        let timeout = Duration::from_secs(config.timeout.unwrap_or(10));
+        Ok(HomeAssistant {
+            config: config.clone(),
+            client: reqwest::blocking::Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|e| ConfigError::new(&format!("home_assistant: {}", e)))?,
+        })
+    }
+}
+
+impl ExternalTemperatureSource for HomeAssistant {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        let config = &self.config;
+        let ha_state_url = format!("{}/api/states/{}", config.host.as_str(), config.entity_id);
+
+        let entity = self
+            .client
+            .get(ha_state_url)
+            .header("Authorization", format!("Bearer {}", config.token))
+            .send()?
+            .error_for_status()?
+            .json::<Value>()?;
+        read_temperature(&entity, config.temperature_field.as_deref())
+            .map_err(|e| ExternalTemperatureErr::from(format!("{}: {}", config.entity_id, e)))
+    }
+}
+
+/// Temperature in °C of an entity state, from its state or an attribute
+/// path, converted from °F when that is its unit: `temperature_unit` for
+/// weather entities, `unit_of_measurement` otherwise.
+pub fn read_temperature(
+    entity: &Value,
+    field: Option<&str>,
+) -> Result<f32, ExternalTemperatureErr> {
+    let state = entity.get("state").and_then(Value::as_str);
+    if let Some(state @ ("unavailable" | "unknown")) = state {
+        return Err(ExternalTemperatureErr::from(state.to_string()));
+    }
+
+    let attributes = entity.get("attributes");
+    let (value, unit) = match field {
+        None => (
+            entity.get("state"),
+            attributes.and_then(|a| a.get("unit_of_measurement")),
+        ),
+        Some(path) => (
+            attributes.and_then(|a| lookup(a, path)),
+            attributes.and_then(|a| a.get("temperature_unit").or(a.get("unit_of_measurement"))),
+        ),
+    };
+    let value = match value {
+        Some(Value::Number(n)) => n.as_f64().map(|n| n as f32),
+        Some(Value::String(s)) => s.trim().parse::<f32>().ok(),
+        Some(_) => None,
+        None => {
+            return Err(ExternalTemperatureErr::from(format!(
                 "Unknown field {}",
-                config.temperature_field
-            )))?;
+                field.unwrap_or("state")
+            )))
+        }
+    }
+    .ok_or_else(|| {
+        ExternalTemperatureErr::from(format!("Cannot parse {}", field.unwrap_or("state")))
+    })?;
 
-    return temp_str
-        .to_string()
-        .parse::<f32>()
-        .map_err(|e| ExternalTemperatureErr::from(format!("Cannot parse: {}", e.to_string())));
+    match unit.and_then(Value::as_str) {
+        Some("°F" | "F") => Ok((value - 32.0) * 5.0 / 9.0),
+        _ => Ok(value),
+    }
+}
+
+/// Value at a dotted path, numbers indexing lists.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sensor_state() {
+        let sensor = json!({
+            "entity_id": "sensor.outdoor",
+            "state": "12.5",
+            "attributes": {"unit_of_measurement": "°C"}
+        });
+        assert_eq!(read_temperature(&sensor, None), Ok(12.5));
+
+        let fahrenheit = json!({
+            "state": "50",
+            "attributes": {"unit_of_measurement": "°F"}
+        });
+        assert_eq!(read_temperature(&fahrenheit, None), Ok(10.0));
+
+        let unavailable = json!({"state": "unavailable", "attributes": {}});
+        assert!(read_temperature(&unavailable, None).is_err());
+        assert!(read_temperature(&unavailable, Some("temperature")).is_err());
+    }
+
+    #[test]
+    fn test_attribute_path() {
+        let weather = json!({
+            "state": "cloudy",
+            "attributes": {
+                "temperature": 8.2,
+                "temperature_unit": "°C",
+                "forecast": [{"temperature": "6"}]
+            }
+        });
+        assert_eq!(read_temperature(&weather, Some("temperature")), Ok(8.2));
+        assert_eq!(
+            read_temperature(&weather, Some("forecast.0.temperature")),
+            Ok(6.0)
+        );
+        assert!(read_temperature(&weather, Some("forecast.1.temperature")).is_err());
+        assert!(read_temperature(&weather, None).is_err());
+
+        let sensor = json!({
+            "state": "on",
+            "attributes": {"current_temperature": 50, "unit_of_measurement": "°F"}
+        });
+        assert_eq!(
+            read_temperature(&sensor, Some("current_temperature")),
+            Ok(10.0)
+        );
+    }
 }
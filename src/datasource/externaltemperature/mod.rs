@@ -1,9 +1,149 @@
+use std::time::Duration;
 use std::{error, fmt};
 
+use crate::config::{Config, ConfigError, Satellite, TemperatureSource};
+use composite::{Combine, Composite, Member};
+
+pub mod command;
+pub mod composite;
+pub mod file;
+pub mod fixed;
 pub mod homeassistant;
+pub mod mqtt;
+pub mod onewire;
+pub mod openmeteo;
 
 pub trait ExternalTemperatureSource {
     fn get(&mut self) -> Result<f32, ExternalTemperatureErr>;
+
+    /// Age of the value returned by the last `get`, zero when read live.
+    fn age(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Outdoor source selected by `[outdoor] source`, Home Assistant by default,
+/// or the composite of `[outdoor] sources`.
+pub fn outdoor(config: &Config) -> Result<Box<dyn ExternalTemperatureSource>, ConfigError> {
+    let outdoor = config.outdoor.clone().unwrap_or_default();
+    build("outdoor", &outdoor.input, config)
+}
+
+/// Ambient temperature of the satellite at `index` in `[[satellites]]`.
+pub fn indoor(
+    config: &Config,
+    index: usize,
+    satellite: &Satellite,
+) -> Result<Box<dyn ExternalTemperatureSource>, ConfigError> {
+    build(
+        &format!("satellites.{}.indoor", index),
+        &satellite.indoor,
+        config,
+    )
+}
+
+/// Source of a `section` table, or the composite of its `sources`.
+fn build(
+    section: &str,
+    input: &TemperatureSource,
+    config: &Config,
+) -> Result<Box<dyn ExternalTemperatureSource>, ConfigError> {
+    let Some(names) = &input.sources else {
+        return source(
+            input.source.as_deref().unwrap_or("homeassistant"),
+            section,
+            input,
+            config,
+        );
+    };
+    if input.source.is_some() {
+        return Err(ConfigError::new(&format!(
+            "{0}.source and {0}.sources are exclusive",
+            section
+        )));
+    }
+    if names.is_empty() {
+        return Err(ConfigError::new(&format!("{}.sources is empty", section)));
+    }
+    let combine = Combine::from_name(input.combine.as_deref().unwrap_or("first"))
+        .map_err(|e| ConfigError::new(&format!("{}.{}", section, e)))?;
+    let max_age = input.max_age.clone().unwrap_or_default();
+    if let Some(name) = max_age.keys().find(|name| !names.contains(name)) {
+        return Err(ConfigError::new(&format!(
+            "{0}.max_age: {1} is not in {0}.sources",
+            section, name
+        )));
+    }
+    let members = names
+        .iter()
+        .map(|name| {
+            let max_age = max_age.get(name).copied().map(Duration::from_secs);
+            Ok(Member::new(
+                name,
+                source(name, section, input, config)?,
+                max_age,
+            ))
+        })
+        .collect::<Result<Vec<_>, ConfigError>>()?;
+    Ok(Box::new(Composite::new(members, combine)))
+}
+
+/// Source configured in the `[<section>.<name>]` table.
+fn source(
+    name: &str,
+    section: &str,
+    input: &TemperatureSource,
+    config: &Config,
+) -> Result<Box<dyn ExternalTemperatureSource>, ConfigError> {
+    let missing =
+        |table: &str| ConfigError::new(&format!("missing required config: {}.{}", section, table));
+    Ok(match name {
+        "homeassistant" => Box::new(homeassistant::HomeAssistant::new(
+            input
+                .homeassistant
+                .as_ref()
+                .or(config.home_assistant.as_ref())
+                .ok_or_else(|| ConfigError::new("missing required config: home_assistant"))?,
+        )?),
+        "mqtt" => Box::new(mqtt::Mqtt::new(
+            input.mqtt.as_ref().ok_or_else(|| missing("mqtt"))?,
+            config.mqtt.as_ref(),
+            section,
+        )?),
+        "onewire" => Box::new(onewire::OneWire::new(
+            input.onewire.as_ref().ok_or_else(|| missing("onewire"))?,
+        )),
+        "openmeteo" => Box::new(openmeteo::OpenMeteo::new(
+            input.openmeteo.as_ref().ok_or_else(|| missing("openmeteo"))?,
+        )?),
+        "file" => Box::new(file::File::new(
+            input.file.as_ref().ok_or_else(|| missing("file"))?,
+        )),
+        "command" => Box::new(command::Command::new(
+            input.command.as_ref().ok_or_else(|| missing("command"))?,
+        )),
+        "static" => Box::new(fixed::Fixed::new(
+            input.r#static.as_ref().ok_or_else(|| missing("static"))?,
+        )),
+        source => {
+            return Err(ConfigError::new(&format!(
+                "unknown {} source {}, expected homeassistant, mqtt, onewire, openmeteo, file, command or static",
+                section, source
+            )))
+        }
+    })
+}
+
+/// Number in °C, e.g. a file or command output.
+pub fn parse_temperature(s: &str) -> Result<f32, ExternalTemperatureErr> {
+    s.trim()
+        .parse::<f32>()
+        .map_err(|_| ExternalTemperatureErr::from(format!("Cannot parse: {:?}", s.trim())))
+}
+
+/// `value * scale + offset`, e.g. for a reading in 0.1°C or a calibration.
+pub fn scale(value: f32, scale: Option<f32>, offset: Option<f32>) -> f32 {
+    value * scale.unwrap_or(1.0) + offset.unwrap_or(0.0)
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -24,3 +164,90 @@ impl From<String> for ExternalTemperatureErr {
         ExternalTemperatureErr { msg: err }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(outdoor: &str) -> Config {
+        toml::from_str(outdoor).unwrap()
+    }
+
+    #[test]
+    fn test_outdoor_factory() {
+        let mut source = outdoor(&config(
+            r#"
+            [outdoor]
+            source = "static"
+            [outdoor.static]
+            value = 4.5
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(source.get(), Ok(4.5));
+
+        // home assistant by default
+        assert!(outdoor(&config("")).is_err());
+        assert!(outdoor(&config("[outdoor]\nsource = \"file\"")).is_err());
+        assert!(outdoor(&config("[outdoor]\nsource = \"sonde\"")).is_err());
+    }
+
+    #[test]
+    fn test_outdoor_sources() {
+        let sources = r#"
+            [outdoor]
+            sources = ["file", "static"]
+            [outdoor.file]
+            path = "/nonexistent"
+            [outdoor.static]
+            value = 4.5
+            "#;
+        assert_eq!(outdoor(&config(sources)).unwrap().get(), Ok(4.5));
+
+        let max_age = format!("{}[outdoor.max_age]\nmqtt = 60", sources);
+        assert!(outdoor(&config(&max_age)).is_err());
+        let combine = sources.replace("[outdoor]", "[outdoor]\ncombine = \"max\"");
+        assert!(outdoor(&config(&combine)).is_err());
+        let both = sources.replace("[outdoor]", "[outdoor]\nsource = \"file\"");
+        assert!(outdoor(&config(&both)).is_err());
+    }
+
+    #[test]
+    fn test_indoor() {
+        let satellites = config(
+            r#"
+            [[satellites]]
+            zone = 1
+            setpoint = 20.0
+            [satellites.indoor]
+            sources = ["file", "static"]
+            [satellites.indoor.file]
+            path = "/nonexistent"
+            [satellites.indoor.static]
+            value = 19.5
+
+            # entity table of earlier versions
+            [[satellites]]
+            zone = 2
+            setpoint = 20.0
+            [satellites.indoor]
+            host = "http://ha:8123"
+            port = 8123
+            token = "secret"
+            entity_id = "sensor.bedroom"
+            "#,
+        );
+        let satellites_ = satellites.satellites.as_ref().unwrap();
+        let mut first = indoor(&satellites, 0, &satellites_[0]).unwrap();
+        assert_eq!(first.get(), Ok(19.5));
+        let second = &satellites_[1].indoor;
+        assert_eq!(
+            second
+                .homeassistant
+                .as_ref()
+                .map(|ha| ha.entity_id.as_str()),
+            Some("sensor.bedroom")
+        );
+        assert!(indoor(&satellites, 1, &satellites_[1]).is_ok());
+    }
+}
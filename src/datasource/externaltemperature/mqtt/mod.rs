@@ -0,0 +1,217 @@
+use paho_mqtt::{
+    AsyncClient, ConnectOptions, ConnectOptionsBuilder, CreateOptionsBuilder, Message,
+};
+use serde_json::Value;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::config::{self, ConfigError};
+use crate::datasource::externaltemperature::{
+    parse_temperature, ExternalTemperatureErr, ExternalTemperatureSource,
+};
+
+/// Timeout of the broker connection and subscription.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Last temperature published on a topic, e.g. by Zigbee2MQTT or a Shelly.
+pub struct Mqtt {
+    client: AsyncClient,
+    /// messages with the time they arrived
+    rx: Receiver<(Message, Instant)>,
+    conn_opts: ConnectOptions,
+    topic: String,
+    pointer: Option<String>,
+    max_age: Duration,
+    last: Option<(f32, Instant)>,
+}
+
+impl Mqtt {
+    /// Connects on the first read, so a broker down at start is retried.
+    /// The client id defaults to `frisquet-<section>`.
+    pub fn new(
+        config: &config::MqttSource,
+        mqtt: Option<&config::MQTT>,
+        section: &str,
+    ) -> Result<Mqtt, ConfigError> {
+        // same broker and credentials as the radio unless set
+        let shared = match &config.broker {
+            Some(_) => None,
+            None => mqtt,
+        };
+        let broker = config
+            .broker
+            .as_ref()
+            .or(shared.map(|m| &m.broker))
+            .ok_or_else(|| {
+                ConfigError::new(&format!("missing required config: {}.mqtt.broker", section))
+            })?;
+        let client_id = format!("frisquet-{}", section);
+        let create_opts = CreateOptionsBuilder::new()
+            .server_uri(broker)
+            .client_id(config.client_id.as_deref().unwrap_or(&client_id))
+            .finalize();
+        let client = AsyncClient::new(create_opts).map_err(|e| ConfigError::new(&e.to_string()))?;
+        let (tx, rx) = channel();
+        client.set_message_callback(move |_, msg| {
+            if let Some(msg) = msg {
+                let _ = tx.send((msg, Instant::now()));
+            }
+        });
+
+        let mut conn_opts = ConnectOptionsBuilder::new();
+        conn_opts
+            .keep_alive_interval(Duration::from_secs(20))
+            .clean_session(true);
+        if let Some(username) = config
+            .username
+            .as_ref()
+            .or(shared.and_then(|m| m.username.as_ref()))
+        {
+            conn_opts.user_name(username);
+        }
+        if let Some(password) = config
+            .password
+            .as_ref()
+            .or(shared.and_then(|m| m.password.as_ref()))
+        {
+            conn_opts.password(password);
+        }
+
+        Ok(Mqtt {
+            client,
+            rx,
+            conn_opts: conn_opts.finalize(),
+            topic: config.topic.clone(),
+            pointer: config.pointer.clone(),
+            max_age: Duration::from_secs(config.max_age.unwrap_or(900)),
+            last: None,
+        })
+    }
+
+    fn connect(&self) -> Result<(), ExternalTemperatureErr> {
+        self.client
+            .connect(self.conn_opts.clone())
+            .wait_for(TIMEOUT)
+            .map_err(|e| ExternalTemperatureErr::from(format!("Unable to connect: {}", e)))?;
+        self.client
+            .subscribe(&self.topic, 0)
+            .wait_for(TIMEOUT)
+            .map_err(|e| ExternalTemperatureErr::from(format!("{}: {}", self.topic, e)))?;
+        Ok(())
+    }
+
+    /// Keep the value of a message received at `at`. Retained messages are
+    /// sent again on each subscription, a dead sensor would look alive, so
+    /// they are ignored.
+    fn receive(&mut self, msg: &Message, at: Instant) {
+        if msg.retained() {
+            return;
+        }
+        match parse_payload(&msg.payload_str(), self.pointer.as_deref()) {
+            Ok(value) => self.last = Some((value, at)),
+            Err(e) => println!("{}: {}", self.topic, e),
+        }
+    }
+
+    /// Last value, an error when older than `max_age`.
+    fn latest(&self, now: Instant) -> Result<f32, ExternalTemperatureErr> {
+        match self.last {
+            Some((value, at)) if now.duration_since(at) <= self.max_age => Ok(value),
+            Some((_, at)) => Err(ExternalTemperatureErr::from(format!(
+                "{}: no value for {}s",
+                self.topic,
+                now.duration_since(at).as_secs()
+            ))),
+            None => Err(ExternalTemperatureErr::from(format!(
+                "{}: no value yet",
+                self.topic
+            ))),
+        }
+    }
+}
+
+impl ExternalTemperatureSource for Mqtt {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        if !self.client.is_connected() {
+            self.connect()?;
+        }
+        // queued messages, the last one wins
+        while let Ok((msg, at)) = self.rx.try_recv() {
+            self.receive(&msg, at);
+        }
+        self.latest(Instant::now())
+    }
+}
+
+/// Temperature of a payload, at a JSON pointer such as `/temperature` or the
+/// whole payload.
+pub fn parse_payload(payload: &str, pointer: Option<&str>) -> Result<f32, ExternalTemperatureErr> {
+    let Some(pointer) = pointer else {
+        return parse_temperature(payload);
+    };
+    let json: Value = serde_json::from_str(payload)
+        .map_err(|e| ExternalTemperatureErr::from(format!("Invalid JSON: {}", e)))?;
+    match json.pointer(pointer) {
+        Some(Value::Number(n)) => n
+            .as_f64()
+            .map(|n| n as f32)
+            .ok_or_else(|| ExternalTemperatureErr::from(format!("Cannot parse {}", pointer))),
+        Some(Value::String(s)) => parse_temperature(s),
+        Some(_) => Err(ExternalTemperatureErr::from(format!(
+            "Cannot parse {}",
+            pointer
+        ))),
+        None => Err(ExternalTemperatureErr::from(format!(
+            "Unknown field {}",
+            pointer
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_payload() {
+        let zigbee = r#"{"battery":97,"temperature":6.38,"humidity":81}"#;
+        assert_eq!(parse_payload(zigbee, Some("/temperature")), Ok(6.38));
+        assert!(parse_payload(zigbee, Some("/pressure")).is_err());
+        assert!(parse_payload(zigbee, None).is_err());
+
+        let shelly = r#"{"id":0,"tC":"21.4","tF":70.5}"#;
+        assert_eq!(parse_payload(shelly, Some("/tC")), Ok(21.4));
+        assert_eq!(parse_payload(" -2.5\n", None), Ok(-2.5));
+    }
+
+    #[test]
+    fn test_staleness() {
+        let mut source = Mqtt::new(
+            &config::MqttSource {
+                broker: Some("tcp://localhost:1883".into()),
+                client_id: None,
+                username: None,
+                password: None,
+                topic: "zigbee2mqtt/outdoor".into(),
+                pointer: Some("/temperature".into()),
+                max_age: Some(60),
+            },
+            None,
+            "outdoor",
+        )
+        .unwrap();
+        let now = Instant::now();
+        assert!(source.latest(now).is_err());
+
+        let topic = "zigbee2mqtt/outdoor";
+        source.receive(
+            &Message::new_retained(topic, r#"{"temperature":3}"#, 0),
+            now,
+        );
+        assert!(source.latest(now).is_err());
+
+        source.receive(&Message::new(topic, r#"{"temperature":4.5}"#, 0), now);
+        assert_eq!(source.latest(now + Duration::from_secs(60)), Ok(4.5));
+        assert!(source.latest(now + Duration::from_secs(61)).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::PathBuf;
+
+use crate::config;
+use crate::datasource::externaltemperature::{
+    scale, ExternalTemperatureErr, ExternalTemperatureSource,
+};
+
+/// Sysfs directory of the w1-gpio/w1-therm kernel modules.
+const W1_DEVICES: &str = "/sys/bus/w1/devices";
+
+/// DS18B20 probe read through the kernel 1-Wire driver.
+pub struct OneWire {
+    dir: PathBuf,
+    device: Option<String>,
+    scale: Option<f32>,
+    offset: Option<f32>,
+}
+
+impl OneWire {
+    pub fn new(config: &config::OneWireSource) -> OneWire {
+        OneWire {
+            dir: PathBuf::from(W1_DEVICES),
+            device: config.device.clone(),
+            scale: config.scale,
+            offset: config.offset,
+        }
+    }
+
+    /// The configured device, or the only DS18B20 (family 28) found.
+    fn device(&self) -> Result<String, ExternalTemperatureErr> {
+        if let Some(device) = &self.device {
+            return Ok(device.clone());
+        }
+        let err = |e: std::io::Error| {
+            ExternalTemperatureErr::from(format!("{}: {}", self.dir.display(), e))
+        };
+        let mut devices = vec![];
+        for entry in read_dir(&self.dir).map_err(err)? {
+            let name = entry
+                .map_err(err)?
+                .file_name()
+                .to_string_lossy()
+                .to_string();
+            if name.starts_with("28-") {
+                devices.push(name);
+            }
+        }
+        match devices.as_slice() {
+            [device] => Ok(device.clone()),
+            [] => Err(ExternalTemperatureErr::from(format!(
+                "no DS18B20 in {}",
+                self.dir.display()
+            ))),
+            _ => Err(ExternalTemperatureErr::from(format!(
+                "several DS18B20, set device to one of {}",
+                devices.join(", ")
+            ))),
+        }
+    }
+}
+
+impl ExternalTemperatureSource for OneWire {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        let path = self.dir.join(self.device()?).join("w1_slave");
+        let content = read_to_string(&path)
+            .map_err(|e| ExternalTemperatureErr::from(format!("{}: {}", path.display(), e)))?;
+        Ok(scale(parse_w1_slave(&content)?, self.scale, self.offset))
+    }
+}
+
+/// °C of a `w1_slave` reading, refused when the CRC check failed:
+/// ```text
+/// 72 01 4b 46 7f ff 0e 10 57 : crc=57 YES
+/// 72 01 4b 46 7f ff 0e 10 57 t=23125
+/// ```
+pub fn parse_w1_slave(content: &str) -> Result<f32, ExternalTemperatureErr> {
+    let mut lines = content.lines();
+    if !lines
+        .next()
+        .is_some_and(|line| line.trim_end().ends_with("YES"))
+    {
+        return Err(ExternalTemperatureErr::from("CRC check failed".to_string()));
+    }
+    let millis = lines
+        .next()
+        .and_then(|line| line.split_once("t="))
+        .and_then(|(_, t)| t.trim().parse::<i32>().ok())
+        .ok_or_else(|| ExternalTemperatureErr::from(format!("Cannot parse: {:?}", content)))?;
+    // 85°C is the power-on value, read before any conversion
+    if millis == 85000 {
+        return Err(ExternalTemperatureErr::from(
+            "no conversion yet".to_string(),
+        ));
+    }
+    Ok(millis as f32 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    fn test_parse_w1_slave() {
+        let ok = "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert_eq!(parse_w1_slave(ok), Ok(23.125));
+        let negative =
+            "5e ff 4b 46 7f ff 02 10 7d : crc=7d YES\n5e ff 4b 46 7f ff 02 10 7d t=-10125\n";
+        assert_eq!(parse_w1_slave(negative), Ok(-10.125));
+        assert!(parse_w1_slave(&ok.replace("YES", "NO")).is_err());
+        assert!(parse_w1_slave(&ok.replace("t=23125", "t=85000")).is_err());
+    }
+
+    #[test]
+    fn test_find_device() {
+        let dir = std::env::temp_dir().join(format!("frisquet-w1-{}", std::process::id()));
+        let probe = dir.join("28-0316a2794aff");
+        create_dir_all(&probe).unwrap();
+        create_dir_all(dir.join("w1_bus_master1")).unwrap();
+        write(probe.join("w1_slave"), "00 : crc=57 YES\n00 t=6500\n").unwrap();
+
+        let mut source = OneWire {
+            dir: dir.clone(),
+            device: None,
+            scale: None,
+            offset: Some(-0.5),
+        };
+        assert_eq!(source.get(), Ok(6.0));
+
+        create_dir_all(dir.join("28-0316a2794b00")).unwrap();
+        assert!(source.get().is_err());
+        source.device = Some("28-0316a2794aff".into());
+        assert_eq!(source.get(), Ok(6.0));
+        remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, ConfigError};
+use crate::datasource::externaltemperature::{ExternalTemperatureErr, ExternalTemperatureSource};
+
+const DEFAULT_URL: &str = "https://api.open-meteo.com";
+
+/// Current temperature at a location from an Open-Meteo compatible API,
+/// requested at most once per `refresh`.
+pub struct OpenMeteo {
+    url: String,
+    refresh: Duration,
+    client: reqwest::blocking::Client,
+    /// last value and when it was fetched
+    cached: Option<(f32, Instant)>,
+    last_request: Option<Instant>,
+}
+
+impl OpenMeteo {
+    pub fn new(config: &config::OpenMeteoSource) -> Result<OpenMeteo, ConfigError> {
+        let url = format!(
+            "{}/v1/forecast?latitude={}&longitude={}&current=temperature_2m&temperature_unit=celsius",
+            config.url.as_deref().unwrap_or(DEFAULT_URL).trim_end_matches('/'),
+            config.latitude,
+            config.longitude
+        );
+        Ok(OpenMeteo {
+            url,
+            refresh: Duration::from_secs(config.refresh.unwrap_or(900)),
+            client: reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(config.timeout.unwrap_or(10)))
+                .build()
+                .map_err(|e| ConfigError::new(&format!("outdoor.openmeteo: {}", e)))?,
+            cached: None,
+            last_request: None,
+        })
+    }
+
+    fn fetch(&self) -> Result<f32, ExternalTemperatureErr> {
+        let json = self
+            .client
+            .get(&self.url)
+            .send()?
+            .error_for_status()?
+            .json::<Value>()?;
+        parse_current(&json)
+    }
+}
+
+impl ExternalTemperatureSource for OpenMeteo {
+    fn get(&mut self) -> Result<f32, ExternalTemperatureErr> {
+        let now = Instant::now();
+        if let Some((value, at)) = self.cached {
+            if now.duration_since(at) < self.refresh {
+                return Ok(value);
+            }
+        }
+        // failed requests are not retried before the interval either
+        if let Some(at) = self.last_request {
+            if now.duration_since(at) < self.refresh {
+                return Err(ExternalTemperatureErr::from(format!(
+                    "next request in {}s",
+                    (self.refresh - now.duration_since(at)).as_secs()
+                )));
+            }
+        }
+        self.last_request = Some(now);
+        let value = self.fetch()?;
+        self.cached = Some((value, now));
+        Ok(value)
+    }
+}
+
+/// `current.temperature_2m` of a forecast response.
+pub fn parse_current(json: &Value) -> Result<f32, ExternalTemperatureErr> {
+    if let Some(reason) = json.get("reason").and_then(Value::as_str) {
+        return Err(ExternalTemperatureErr::from(reason.to_string()));
+    }
+    json.pointer("/current/temperature_2m")
+        .and_then(Value::as_f64)
+        .map(|t| t as f32)
+        .ok_or_else(|| {
+            ExternalTemperatureErr::from("Unknown field current.temperature_2m".to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_parse_current() {
+        let response = json!({
+            "latitude": 48.86,
+            "longitude": 2.34,
+            "current_units": {"time": "iso8601", "temperature_2m": "°C"},
+            "current": {"time": "2024-01-15T10:00", "interval": 900, "temperature_2m": 3.4}
+        });
+        assert_eq!(parse_current(&response), Ok(3.4));
+        let error = json!({"error": true, "reason": "Latitude must be in range of -90 to 90°."});
+        assert!(parse_current(&error).is_err());
+    }
+
+    #[test]
+    fn test_cached() {
+        // stub answering a single request
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let n = stream.read(&mut request).unwrap();
+            let body = r#"{"current":{"temperature_2m":-1.5}}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let mut source = OpenMeteo::new(&config::OpenMeteoSource {
+            latitude: 48.86,
+            longitude: 2.34,
+            url: Some(url),
+            refresh: Some(600),
+            timeout: Some(5),
+        })
+        .unwrap();
+        assert_eq!(source.get(), Ok(-1.5));
+        let request = server.join().unwrap();
+        assert!(request
+            .starts_with("GET /v1/forecast?latitude=48.86&longitude=2.34&current=temperature_2m"));
+        // no second request, the stub is gone
+        assert_eq!(source.get(), Ok(-1.5));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::config::{ConfigError, Outdoor};
+use crate::connect::sonde::{MAX_TEMPERATURE, MIN_TEMPERATURE};
+
+const DEFAULT_MAX_STEP: f32 = 2.0;
+const DEFAULT_STALE_AFTER: u64 = 15 * 60;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    None,
+    /// exponential moving average, weight of the new value
+    Ema(f32),
+    /// mean of the last values
+    Mean(usize),
+}
+
+/// Turns outdoor source readings into the temperatures sent by the emulated
+/// sonde: clamped, smoothed and rate-limited, the last good value is kept
+/// when the source fails until it goes stale.
+pub struct SondeEmulator {
+    min: f32,
+    max: f32,
+    max_step: f32,
+    filter: Filter,
+    stale_after: Duration,
+
+    window: VecDeque<f32>,
+    last: Option<(f32, Instant)>,
+}
+
+impl SondeEmulator {
+    pub fn new(config: &Outdoor) -> Result<SondeEmulator, ConfigError> {
+        let filter = match config.filter.as_deref().unwrap_or("none") {
+            "none" => Filter::None,
+            "ema" => {
+                let alpha = config.alpha.unwrap_or(0.5);
+                if !(alpha > 0.0 && alpha <= 1.0) {
+                    return Err(ConfigError::new("outdoor.alpha should be in ]0, 1]"));
+                }
+                Filter::Ema(alpha)
+            }
+            "mean" => match config.window.unwrap_or(3) {
+                0 => return Err(ConfigError::new("outdoor.window should be at least 1")),
+                window => Filter::Mean(window),
+            },
+            x => {
+                return Err(ConfigError::new(&format!("invalid outdoor.filter: {}", x)));
+            }
+        };
+
+        let min = config.min.unwrap_or(MIN_TEMPERATURE).max(MIN_TEMPERATURE);
+        let max = config.max.unwrap_or(MAX_TEMPERATURE).min(MAX_TEMPERATURE);
+        if min > max {
+            return Err(ConfigError::new(
+                "outdoor.min should be lower than outdoor.max",
+            ));
+        }
+
+        Ok(SondeEmulator {
+            min,
+            max,
+            max_step: config.max_step.unwrap_or(DEFAULT_MAX_STEP).abs(),
+            filter,
+            stale_after: Duration::from_secs(config.stale_after.unwrap_or(DEFAULT_STALE_AFTER)),
+            window: VecDeque::new(),
+            last: None,
+        })
+    }
+
+    /// Last good value, e.g. saved before a restart, read `age` ago. Values
+    /// already stale are ignored.
+    pub fn restore(&mut self, value: f32, age: Duration, now: Instant) {
+        if age > self.stale_after || !value.is_finite() {
+            return;
+        }
+        if let Some(at) = now.checked_sub(age) {
+            self.last = Some((value.clamp(self.min, self.max), at));
+        }
+    }
+
+    /// Temperature to send for a new source reading, `None` when there is no
+    /// value fresh enough and the sonde should stop sending.
+    pub fn update<E>(&mut self, reading: Result<f32, E>, now: Instant) -> Option<f32> {
+        // a stale value no longer filters nor limits the next reading
+        if self
+            .last
+            .is_some_and(|(_, at)| now.duration_since(at) > self.stale_after)
+        {
+            self.last = None;
+            self.window.clear();
+        }
+
+        let value = match reading {
+            Ok(value) if value.is_finite() => value.clamp(self.min, self.max),
+            _ => return self.last.map(|(value, _)| value),
+        };
+
+        let value = match self.filter {
+            Filter::None => value,
+            Filter::Ema(alpha) => match self.last {
+                Some((last, _)) => alpha * value + (1.0 - alpha) * last,
+                None => value,
+            },
+            Filter::Mean(size) => {
+                self.window.push_back(value);
+                while self.window.len() > size {
+                    self.window.pop_front();
+                }
+                self.window.iter().sum::<f32>() / self.window.len() as f32
+            }
+        };
+
+        let value = match self.last {
+            Some((last, _)) => last + (value - last).clamp(-self.max_step, self.max_step),
+            None => value,
+        };
+        let value = (value * 10.0).round() / 10.0;
+
+        self.last = Some((value, now));
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outdoor() -> Outdoor {
+        Outdoor {
+            min: Some(-20.0),
+            max: Some(40.0),
+            max_step: Some(1.0),
+            filter: None,
+            alpha: None,
+            window: None,
+            stale_after: Some(60),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_clamp_and_rate_limit() {
+        let mut sonde = SondeEmulator::new(&outdoor()).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(sonde.update::<()>(Ok(-35.0), now), Some(-20.0));
+        assert_eq!(sonde.update::<()>(Ok(-10.0), now), Some(-19.0));
+        assert_eq!(sonde.update::<()>(Ok(-18.64), now), Some(-18.6));
+        assert_eq!(sonde.update::<()>(Ok(f32::NAN), now), Some(-18.6));
+    }
+
+    #[test]
+    fn test_stale() {
+        let mut sonde = SondeEmulator::new(&outdoor()).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(sonde.update(Err("down"), now), None);
+        assert_eq!(sonde.update::<()>(Ok(5.0), now), Some(5.0));
+        assert_eq!(
+            sonde.update(Err("down"), now + Duration::from_secs(60)),
+            Some(5.0)
+        );
+        assert_eq!(
+            sonde.update(Err("down"), now + Duration::from_secs(61)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recovered() {
+        let mut config = outdoor();
+        config.filter = Some("mean".into());
+        let mut sonde = SondeEmulator::new(&config).unwrap();
+        let now = Instant::now();
+        assert_eq!(sonde.update::<()>(Ok(-5.0), now), Some(-5.0));
+
+        // down for hours, the first reading is sent as is
+        let later = now + Duration::from_secs(4 * 3600);
+        assert_eq!(sonde.update(Err("down"), later), None);
+        assert_eq!(sonde.update::<()>(Ok(12.0), later), Some(12.0));
+        assert_eq!(sonde.update::<()>(Ok(14.0), later), Some(13.0));
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut config = outdoor();
+        config.max_step = Some(10.0);
+        config.filter = Some("ema".into());
+        config.alpha = Some(0.25);
+        let mut sonde = SondeEmulator::new(&config).unwrap();
+        let now = Instant::now();
+        assert_eq!(sonde.update::<()>(Ok(10.0), now), Some(10.0));
+        assert_eq!(sonde.update::<()>(Ok(14.0), now), Some(11.0));
+
+        config.filter = Some("mean".into());
+        config.window = Some(2);
+        let mut sonde = SondeEmulator::new(&config).unwrap();
+        assert_eq!(sonde.update::<()>(Ok(10.0), now), Some(10.0));
+        assert_eq!(sonde.update::<()>(Ok(14.0), now), Some(12.0));
+        assert_eq!(sonde.update::<()>(Ok(20.0), now), Some(17.0));
+
+        config.filter = Some("median".into());
+        assert!(SondeEmulator::new(&config).is_err());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut sonde = SondeEmulator::new(&outdoor()).unwrap();
+        let now = Instant::now();
+        sonde.restore(7.0, Duration::from_secs(61), now);
+        assert_eq!(sonde.update(Err("down"), now), None);
+
+        sonde.restore(7.0, Duration::from_secs(30), now);
+        assert_eq!(sonde.update(Err("down"), now), Some(7.0));
+        assert_eq!(
+            sonde.update(Err("down"), now + Duration::from_secs(31)),
+            None
+        );
+
+        // rate limited from the restored value
+        sonde.restore(7.0, Duration::from_secs(30), now);
+        assert_eq!(sonde.update::<()>(Ok(12.0), now), Some(8.0));
+    }
+}
@@ -4,18 +4,19 @@ pub mod cmd;
 pub mod config;
 pub mod connect;
 pub mod datasource;
+pub mod emulation;
 pub mod rf;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = cmd::parse();
 
-    println!("frisquet-connect");
+    eprintln!("frisquet-connect");
 
-    // read config
-    let mut config = config::read(&cli.config)?;
-    // setup rf
-    let mut client = rf::new(&config)?;
+    if let Some(result) = cli.run_without_config() {
+        return result;
+    }
 
-    cli.run(&mut client, &mut config)?;
-    return Ok(config.write()?);
+    // read config, commands save their changes to the state file
+    let mut config = config::read(&cli.config, &cli.set)?;
+    cli.run(&mut config)
 }
@@ -14,6 +14,10 @@ pub trait RFClient {
     fn recv_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, RecvTimeoutError>;
     fn send(&mut self, payload: Vec<u8>) -> Result<(), SendError>;
     fn sleep(&mut self) -> Result<(), String>;
+    /// RSSI of the last received frame in dBm, if the radio reports it
+    fn rssi(&self) -> Option<i16> {
+        None
+    }
 }
 
 pub fn new(config: &config::Config) -> Result<Box<dyn RFClient>, String> {
@@ -23,7 +27,7 @@ pub fn new(config: &config::Config) -> Result<Box<dyn RFClient>, String> {
     }
     match &config.serial {
         Some(config) => {
-            println!("frisquet-connect on serial");
+            eprintln!("frisquet-connect on serial");
             return Ok(Box::new(serial::new(&config)?));
         }
         None => {}
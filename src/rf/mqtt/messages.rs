@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize)]
 pub struct DataMessage {
     pub data: String,
+    #[serde(default)]
+    pub rssi: Option<i16>,
 }
 
 #[typetag::serde(tag = "type")]
@@ -14,6 +14,7 @@ pub struct MqttClient {
     client: mqtt::Client,
     rx: Receiver<Option<Message>>,
     cmd_topic: String,
+    rssi: Option<i16>,
 }
 
 pub fn new(config: &config::MQTT) -> Result<MqttClient, String> {
@@ -28,10 +29,17 @@ pub fn new(config: &config::MQTT) -> Result<MqttClient, String> {
     let client = mqtt::Client::new(create_opts).map_err(|e| e.to_string())?;
     let rx: Receiver<Option<Message>> = client.start_consuming();
     // Define the set of options for the connection.
-    let conn_opts = mqtt::ConnectOptionsBuilder::new()
+    let mut conn_opts = mqtt::ConnectOptionsBuilder::new();
+    conn_opts
         .keep_alive_interval(Duration::from_secs(20))
-        .clean_session(true)
-        .finalize();
+        .clean_session(true);
+    if let Some(username) = &config.username {
+        conn_opts.user_name(username);
+    }
+    if let Some(password) = &config.password {
+        conn_opts.password(password);
+    }
+    let conn_opts = conn_opts.finalize();
 
     // Connect and wait for it to complete or fail.
     if let Err(e) = client.connect(conn_opts) {
@@ -46,6 +54,7 @@ pub fn new(config: &config::MQTT) -> Result<MqttClient, String> {
         client: client,
         rx: rx,
         cmd_topic: config.cmd_topic.clone(),
+        rssi: None,
     })
 }
 
@@ -74,6 +83,7 @@ impl RFClient for MqttClient {
             Some(msg) => {
                 let data: messages::DataMessage =
                     serde_json::from_str(msg.payload_str().as_ref()).map_err(|e| e.to_string())?;
+                self.rssi = data.rssi;
                 hex::decode(data.data).map_err(|e| RecvError { msg: e.to_string() })
             }
             None => self.recv(),
@@ -95,6 +105,7 @@ impl RFClient for MqttClient {
             Some(msg) => {
                 let data: messages::DataMessage =
                     serde_json::from_str(msg.payload_str().as_ref()).map_err(|e| e.to_string())?;
+                self.rssi = data.rssi;
                 hex::decode(data.data).map_err(|e| RecvTimeoutError::Error { msg: e.to_string() })
             }
             None => Err(RecvTimeoutError::Timeout),
@@ -111,4 +122,8 @@ impl RFClient for MqttClient {
     fn sleep(&mut self) -> Result<(), String> {
         self.publish(&Sleep {})
     }
+
+    fn rssi(&self) -> Option<i16> {
+        self.rssi
+    }
 }
@@ -10,7 +10,8 @@ use crate::rf::{RFClient, RecvError, RecvTimeoutError, SendError};
 pub struct SerialClient {
     port: Box<dyn serialport::SerialPort>,
     buffer: Vec<u8>,
-    data_packets: VecDeque<Vec<u8>>,
+    data_packets: VecDeque<(Vec<u8>, Option<i16>)>,
+    rssi: Option<i16>,
     mode: Mode,
 }
 
@@ -31,13 +32,14 @@ pub fn new(config: &config::Serial) -> Result<SerialClient, String> {
         port: port,
         buffer: vec![],
         data_packets: VecDeque::new(),
+        rssi: None,
         mode: Mode::Idle,
     })
 }
 
 impl SerialClient {
     fn try_recv(&mut self) -> Result<Option<Vec<u8>>, String> {
-        if let Some(data) = self.data_packets.pop_front() {
+        if let Some(data) = self.pop_packet() {
             return Ok(Some(data));
         }
 
@@ -66,14 +68,18 @@ impl SerialClient {
             }
             if buf[n] == 0xA {
                 // \r
-                let data = hex::decode(&self.buffer)
+                let line = String::from_utf8_lossy(&self.buffer).into_owned();
+                // hex frame, followed by the RSSI with recent firmwares
+                let mut parts = line.split(' ');
+                let data = hex::decode(parts.next().unwrap_or_default())
                     // .map_err(|e| {
                     //     println!("{}: {}", e, String::from_utf8(self.buffer.clone()).unwrap())
                     // })
                     .unwrap_or(vec![]);
+                let rssi = parts.next().and_then(|rssi| rssi.parse().ok());
 
                 if !data.is_empty() {
-                    self.data_packets.push_back(data)
+                    self.data_packets.push_back((data, rssi))
                 }
                 self.buffer.clear();
             } else {
@@ -81,7 +87,13 @@ impl SerialClient {
             }
         }
 
-        Ok(self.data_packets.pop_front())
+        Ok(self.pop_packet())
+    }
+
+    fn pop_packet(&mut self) -> Option<Vec<u8>> {
+        let (data, rssi) = self.data_packets.pop_front()?;
+        self.rssi = rssi;
+        Some(data)
     }
 }
 
@@ -132,6 +144,10 @@ impl RFClient for SerialClient {
             .map_err(|e| SendError { msg: e.to_string() })
     }
 
+    fn rssi(&self) -> Option<i16> {
+        self.rssi
+    }
+
     fn sleep(&mut self) -> Result<(), String> {
         self.mode = Mode::Sleep;
 